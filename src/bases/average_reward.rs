@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use super::function_approximation::{ActionValueFunction, FeatureExtractor};
+use super::mdp::{Action, Enviorment, State};
+use super::semi_gradient::approximate_epsilon_greedy;
+use super::td_control::{action_value, epsilon_greedy};
+
+#[derive(Debug, Clone)]
+pub struct AverageRewardParameters {
+    pub alpha: f32,
+    /// Step size of the average reward estimate.
+    pub beta: f32,
+    pub epsilon: f32,
+    /// Length of the single run, continuing tasks never terminate.
+    pub steps: usize,
+}
+
+impl Default for AverageRewardParameters {
+    fn default() -> Self {
+        AverageRewardParameters {
+            alpha: 0.01,
+            beta: 0.01,
+            epsilon: 0.1,
+            steps: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DifferentialResult<Q> {
+    /// Differential action values q(s, a), relative to the average reward.
+    pub action_values: Q,
+    pub average_reward: f32,
+    /// The estimate R̄ after every step.
+    pub average_rewards: Vec<f32>,
+}
+
+/// Tabular differential Sarsa (Section 10.3) for continuing tasks, a single run of `params.steps`
+/// steps from `init_state` with the TD error δ = R − R̄ + Q(S', A') − Q(S, A) driving both the
+/// action values and the average reward estimate R̄. `td_control::greedy_policy` gives the
+/// learned policy.
+pub fn differential_sarsa<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_state: &S,
+    params: &AverageRewardParameters,
+) -> DifferentialResult<HashMap<(S, A), f32>>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut vals = init_vals;
+    let mut average_reward = 0.0;
+    let mut average_rewards = Vec::with_capacity(params.steps);
+    let mut state = init_state.clone();
+    let mut action = epsilon_greedy(env, &vals, &state, params.epsilon);
+    for _ in 0..params.steps {
+        let (next_state, reward) = env.response(&state, &action);
+        let next_action = epsilon_greedy(env, &vals, &next_state, params.epsilon);
+        let error = reward as f32 - average_reward + action_value(&vals, &next_state, &next_action)
+            - action_value(&vals, &state, &action);
+        average_reward += params.beta * error;
+        *vals.entry((state, action)).or_insert(0.0) += params.alpha * error;
+        average_rewards.push(average_reward);
+        state = next_state;
+        action = next_action;
+    }
+    DifferentialResult {
+        action_values: vals,
+        average_reward,
+        average_rewards,
+    }
+}
+
+/// Differential semi-gradient Sarsa (Section 10.3), `differential_sarsa` with the table replaced
+/// by an action value approximation.
+pub fn differential_semi_gradient_sarsa<'a, E, S, A, F, Q>(
+    env: &E,
+    approximation: Q,
+    features: &F,
+    init_state: &S,
+    params: &AverageRewardParameters,
+) -> DifferentialResult<Q>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+    Q: ActionValueFunction<A>,
+{
+    let mut q = approximation;
+    let mut average_reward = 0.0;
+    let mut average_rewards = Vec::with_capacity(params.steps);
+    let mut state = init_state.clone();
+    let mut x = features.features(&state);
+    let mut action = approximate_epsilon_greedy(env, &q, &x, &state, params.epsilon);
+    for _ in 0..params.steps {
+        let (next_state, reward) = env.response(&state, &action);
+        let next_x = features.features(&next_state);
+        let next_action = approximate_epsilon_greedy(env, &q, &next_x, &next_state, params.epsilon);
+        let error =
+            reward as f32 - average_reward + q.value(&next_x, &next_action) - q.value(&x, &action);
+        average_reward += params.beta * error;
+        q.update(&x, &action, params.alpha * error);
+        average_rewards.push(average_reward);
+        state = next_state;
+        x = next_x;
+        action = next_action;
+    }
+    DifferentialResult {
+        action_values: q,
+        average_reward,
+        average_rewards,
+    }
+}
@@ -0,0 +1,282 @@
+use std::marker::PhantomData;
+
+use super::function_approximation::{FeatureExtractor, StateVector};
+
+/// Box of the state space, used to bring the vector view of a state into [0, 1] in every
+/// dimension before computing features.
+#[derive(Debug, Clone)]
+pub struct Bounds {
+    pub low: Vec<f64>,
+    pub high: Vec<f64>,
+}
+
+impl Bounds {
+    pub fn new(low: Vec<f64>, high: Vec<f64>) -> Self {
+        Bounds { low, high }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.low.len()
+    }
+
+    /// Coordinates scaled to [0, 1], values outside the box are clamped.
+    pub fn normalize(&self, x: &[f64]) -> Vec<f64> {
+        x.iter()
+            .zip(self.low.iter().zip(&self.high))
+            .map(|(x, (low, high))| ((x - low) / (high - low)).clamp(0.0, 1.0))
+            .collect()
+    }
+}
+
+/// Every vector of `dimension` integers in 0..=order, the exponents and frequencies of the
+/// polynomial and Fourier bases.
+fn integer_grid(order: u32, dimension: usize) -> Vec<Vec<u32>> {
+    let mut grid = vec![Vec::new()];
+    for _ in 0..dimension {
+        grid = grid
+            .into_iter()
+            .flat_map(|prefix| {
+                (0..=order).map(move |c| {
+                    let mut v = prefix.clone();
+                    v.push(c);
+                    v
+                })
+            })
+            .collect();
+    }
+    grid
+}
+
+/// Polynomial basis of the given order (Section 9.5.1), x_i(s) = Π_j s_j^c_ij for every vector of
+/// exponents c_i in {0, ..., order}^d, (order + 1)^d features.
+#[derive(Debug, Clone)]
+pub struct PolynomialBasis<S> {
+    pub bounds: Bounds,
+    pub exponents: Vec<Vec<u32>>,
+    state: PhantomData<S>,
+}
+
+impl<S> PolynomialBasis<S> {
+    pub fn new(order: u32, bounds: Bounds) -> Self {
+        PolynomialBasis {
+            exponents: integer_grid(order, bounds.dimension()),
+            bounds,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> FeatureExtractor<S> for PolynomialBasis<S>
+where
+    S: StateVector,
+{
+    fn features(&self, state: &S) -> Vec<f32> {
+        let s = self.bounds.normalize(&state.to_vector());
+        self.exponents
+            .iter()
+            .map(|c| {
+                s.iter()
+                    .zip(c)
+                    .map(|(s, &c)| s.powi(c as i32))
+                    .product::<f64>() as f32
+            })
+            .collect()
+    }
+    fn dimension(&self) -> usize {
+        self.exponents.len()
+    }
+}
+
+/// Fourier cosine basis of the given order (Section 9.5.2), x_i(s) = cos(π sᵀc_i) for every
+/// frequency vector c_i in {0, ..., order}^d.
+#[derive(Debug, Clone)]
+pub struct FourierBasis<S> {
+    pub bounds: Bounds,
+    pub frequencies: Vec<Vec<u32>>,
+    state: PhantomData<S>,
+}
+
+impl<S> FourierBasis<S> {
+    pub fn new(order: u32, bounds: Bounds) -> Self {
+        FourierBasis {
+            frequencies: integer_grid(order, bounds.dimension()),
+            bounds,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> FeatureExtractor<S> for FourierBasis<S>
+where
+    S: StateVector,
+{
+    fn features(&self, state: &S) -> Vec<f32> {
+        let s = self.bounds.normalize(&state.to_vector());
+        self.frequencies
+            .iter()
+            .map(|c| {
+                let dot: f64 = s.iter().zip(c).map(|(s, &c)| s * c as f64).sum();
+                (std::f64::consts::PI * dot).cos() as f32
+            })
+            .collect()
+    }
+    fn dimension(&self) -> usize {
+        self.frequencies.len()
+    }
+}
+
+/// Centers spread evenly over [0, 1]^d, `per_dimension` of them along each axis including both
+/// ends.
+fn even_grid(per_dimension: usize, dimension: usize) -> Vec<Vec<f64>> {
+    let step = 1.0 / (per_dimension.max(2) - 1) as f64;
+    integer_grid(per_dimension as u32 - 1, dimension)
+        .into_iter()
+        .map(|c| c.into_iter().map(|c| c as f64 * step).collect())
+        .collect()
+}
+
+fn squared_distance(x: &[f64], y: &[f64]) -> f64 {
+    x.iter().zip(y).map(|(a, b)| (a - b).powi(2)).sum()
+}
+
+/// Gaussian radial basis functions (Section 9.5.5), x_i(s) = exp(−‖s − c_i‖² / 2σ²) with the
+/// centers and the width σ in normalized coordinates.
+#[derive(Debug, Clone)]
+pub struct RadialBasis<S> {
+    pub bounds: Bounds,
+    pub centers: Vec<Vec<f64>>,
+    pub width: f64,
+    state: PhantomData<S>,
+}
+
+impl<S> RadialBasis<S> {
+    pub fn new(centers: Vec<Vec<f64>>, width: f64, bounds: Bounds) -> Self {
+        RadialBasis {
+            bounds,
+            centers,
+            width,
+            state: PhantomData,
+        }
+    }
+
+    /// Centers on an even grid with `per_dimension` of them along each axis.
+    pub fn grid(per_dimension: usize, width: f64, bounds: Bounds) -> Self {
+        assert!(
+            per_dimension > 0,
+            "the grid needs at least one center per axis"
+        );
+        let centers = even_grid(per_dimension, bounds.dimension());
+        RadialBasis::new(centers, width, bounds)
+    }
+}
+
+impl<S> FeatureExtractor<S> for RadialBasis<S>
+where
+    S: StateVector,
+{
+    fn features(&self, state: &S) -> Vec<f32> {
+        let s = self.bounds.normalize(&state.to_vector());
+        self.centers
+            .iter()
+            .map(|c| (-squared_distance(&s, c) / (2.0 * self.width * self.width)).exp() as f32)
+            .collect()
+    }
+    fn dimension(&self) -> usize {
+        self.centers.len()
+    }
+}
+
+/// Coarse coding (Section 9.5.3), a binary feature for every circle, present when the state lies
+/// inside it. In one dimension the circles are intervals of width twice the radius.
+#[derive(Debug, Clone)]
+pub struct CoarseCoding<S> {
+    pub bounds: Bounds,
+    pub centers: Vec<Vec<f64>>,
+    pub radius: f64,
+    state: PhantomData<S>,
+}
+
+impl<S> CoarseCoding<S> {
+    pub fn circles(centers: Vec<Vec<f64>>, radius: f64, bounds: Bounds) -> Self {
+        CoarseCoding {
+            bounds,
+            centers,
+            radius,
+            state: PhantomData,
+        }
+    }
+
+    /// `count` intervals of the given width with evenly spaced centers over a one dimensional
+    /// state space.
+    pub fn intervals(count: usize, width: f64, bounds: Bounds) -> Self {
+        assert!(count > 0, "coarse coding needs at least one interval");
+        let centers = even_grid(count, 1);
+        CoarseCoding::circles(centers, width / 2.0, bounds)
+    }
+}
+
+impl<S> FeatureExtractor<S> for CoarseCoding<S>
+where
+    S: StateVector,
+{
+    fn features(&self, state: &S) -> Vec<f32> {
+        let s = self.bounds.normalize(&state.to_vector());
+        let radius = self.radius * self.radius;
+        self.centers
+            .iter()
+            .map(|c| {
+                if squared_distance(&s, c) <= radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+    fn dimension(&self) -> usize {
+        self.centers.len()
+    }
+}
+
+/// State aggregation (Section 9.3), each dimension is cut into `groups[j]` equal intervals and
+/// the only active feature is the cell of the state.
+#[derive(Debug, Clone)]
+pub struct StateAggregation<S> {
+    pub bounds: Bounds,
+    pub groups: Vec<usize>,
+    state: PhantomData<S>,
+}
+
+impl<S> StateAggregation<S> {
+    pub fn new(groups: Vec<usize>, bounds: Bounds) -> Self {
+        assert!(
+            groups.iter().all(|&g| g > 0),
+            "every dimension needs at least one group"
+        );
+        StateAggregation {
+            bounds,
+            groups,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> FeatureExtractor<S> for StateAggregation<S>
+where
+    S: StateVector,
+{
+    fn features(&self, state: &S) -> Vec<f32> {
+        let s = self.bounds.normalize(&state.to_vector());
+        let mut index = 0;
+        for (s, &groups) in s.iter().zip(&self.groups) {
+            let group = ((s * groups as f64) as usize).min(groups - 1);
+            index = index * groups + group;
+        }
+        let mut x = vec![0.0; self.dimension()];
+        x[index] = 1.0;
+        x
+    }
+    fn dimension(&self) -> usize {
+        self.groups.iter().product()
+    }
+}
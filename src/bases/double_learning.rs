@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use super::mdp::{Action, Enviorment, State};
+use super::td_control::{action_value, ActionTracker, TdParameters};
+
+#[derive(Debug, Clone)]
+pub struct DoubleLearningResult<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub first_values: HashMap<(S, A), f32>,
+    pub second_values: HashMap<(S, A), f32>,
+    /// Greedy with respect to the sum of both tables.
+    pub policy: HashMap<S, A>,
+    pub returns: Vec<f32>,
+    pub lengths: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DoubleTarget {
+    QLearning,
+    ExpectedSarsa,
+}
+
+/// Double Q-learning (Section 6.7), one table picks the maximizing action and the other one
+/// evaluates it, which removes the maximization bias of Q-learning.
+pub fn double_q_learning<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    tracker: Option<&mut ActionTracker<S, A>>,
+) -> DoubleLearningResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    double_td_control(
+        env,
+        init_vals,
+        init_states,
+        params,
+        DoubleTarget::QLearning,
+        tracker,
+    )
+}
+
+/// Double Expected Sarsa, the expectation is taken under the ε-greedy policy of the table being
+/// updated and evaluated with the other table.
+pub fn double_expected_sarsa<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    tracker: Option<&mut ActionTracker<S, A>>,
+) -> DoubleLearningResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    double_td_control(
+        env,
+        init_vals,
+        init_states,
+        params,
+        DoubleTarget::ExpectedSarsa,
+        tracker,
+    )
+}
+
+fn double_td_control<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    target: DoubleTarget,
+    mut tracker: Option<&mut ActionTracker<S, A>>,
+) -> DoubleLearningResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut first = init_vals.clone();
+    let mut second = init_vals;
+    let mut returns = Vec::new();
+    let mut lengths = Vec::new();
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut total = 0.0;
+        let mut steps = 0;
+        while !env.is_terminal(&state) && steps < params.max_steps {
+            let action = combined_epsilon_greedy(env, &first, &second, &state, params.epsilon);
+            if let Some(tracker) = tracker.as_deref_mut() {
+                tracker.record(&state, &action);
+            }
+            let (next_state, reward) = env.response(&state, &action);
+            total += reward as f32;
+            steps += 1;
+            let (updated, other) = if rng.gen_bool(0.5) {
+                (&mut first, &second)
+            } else {
+                (&mut second, &first)
+            };
+            let next_value = if env.is_terminal(&next_state) {
+                0.0
+            } else {
+                let actions = env.posible_actions(&next_state);
+                let selector = |a: &A| action_value(updated, &next_state, a);
+                match target {
+                    DoubleTarget::QLearning => {
+                        let best = best_actions(&actions, selector);
+                        let best = best.choose(&mut rng).unwrap();
+                        action_value(other, &next_state, best)
+                    }
+                    DoubleTarget::ExpectedSarsa => {
+                        let best = best_actions(&actions, selector);
+                        let n = actions.len() as f32;
+                        actions
+                            .iter()
+                            .map(|a| {
+                                let mut prob = params.epsilon / n;
+                                if best.contains(&a) {
+                                    prob += (1.0 - params.epsilon) / best.len() as f32;
+                                }
+                                prob * action_value(other, &next_state, a)
+                            })
+                            .sum()
+                    }
+                }
+            };
+            let val = updated.entry((state, action)).or_insert(0.0);
+            *val += params.alpha * (reward as f32 + params.gamma * next_value - *val);
+            state = next_state;
+        }
+        returns.push(total);
+        lengths.push(steps);
+        if let Some(tracker) = tracker.as_deref_mut() {
+            tracker.end_episode();
+        }
+    }
+
+    let mut policy = HashMap::new();
+    for (state, _) in first.keys().chain(second.keys()) {
+        if !policy.contains_key(state) {
+            let actions = env.posible_actions(state);
+            let best = best_actions(&actions, |a| {
+                action_value(&first, state, a) + action_value(&second, state, a)
+            });
+            policy.insert(state.clone(), (*best.choose(&mut rng).unwrap()).clone());
+        }
+    }
+    DoubleLearningResult {
+        first_values: first,
+        second_values: second,
+        policy,
+        returns,
+        lengths,
+    }
+}
+
+fn best_actions<A, F>(actions: &[A], value: F) -> Vec<&A>
+where
+    F: Fn(&A) -> f32,
+{
+    let values: Vec<f32> = actions.iter().map(&value).collect();
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    actions
+        .iter()
+        .zip(values)
+        .filter(|(_, v)| *v == max)
+        .map(|(a, _)| a)
+        .collect()
+}
+
+/// ε-greedy with respect to the sum of both tables.
+fn combined_epsilon_greedy<'a, E, S, A>(
+    env: &E,
+    first: &HashMap<(S, A), f32>,
+    second: &HashMap<(S, A), f32>,
+    state: &S,
+    epsilon: f32,
+) -> A
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let actions = env.posible_actions(state);
+    if rng.gen::<f32>() < epsilon {
+        return actions.choose(&mut rng).unwrap().clone();
+    }
+    let best = best_actions(&actions, |a| {
+        action_value(first, state, a) + action_value(second, state, a)
+    });
+    (*best.choose(&mut rng).unwrap()).clone()
+}
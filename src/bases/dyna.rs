@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use super::mdp::{Action, Enviorment, State};
+use super::td_control::{action_value, epsilon_greedy, greedy_policy, max_action_value};
+
+#[derive(Debug, Clone)]
+pub struct DynaParameters {
+    pub alpha: f32,
+    pub gamma: f32,
+    pub epsilon: f32,
+    /// Planning updates after every real step.
+    pub planning_steps: usize,
+    /// Weight of the exploration bonus κ√τ of Dyna-Q+, zero gives plain Dyna-Q.
+    pub kappa: f32,
+    /// Real steps to run, episodes restart from `init_states` whenever they end.
+    pub steps: usize,
+}
+
+impl Default for DynaParameters {
+    fn default() -> Self {
+        DynaParameters {
+            alpha: 0.1,
+            gamma: 0.95,
+            epsilon: 0.1,
+            planning_steps: 5,
+            kappa: 0.0,
+            steps: 3000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DynaResult<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub action_values: HashMap<(S, A), f32>,
+    pub policy: HashMap<S, A>,
+    /// Reward accumulated up to each real step.
+    pub cumulative_rewards: Vec<f32>,
+    /// Length of every finished episode.
+    pub lengths: Vec<usize>,
+}
+
+/// A pair with its last outcome and the step it was last tried on.
+pub type ModelEntry<S, A> = ((S, A), (S, i32), usize);
+
+/// Deterministic tabular model that remembers the last outcome of every pair tried, with the
+/// step it was last tried on.
+#[derive(Debug, Clone)]
+pub struct TabularModel<S, A>
+where
+    S: State,
+    A: Action,
+{
+    entries: Vec<ModelEntry<S, A>>,
+    index: HashMap<(S, A), usize>,
+    states: HashSet<S>,
+}
+
+impl<S, A> TabularModel<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn new() -> Self {
+        TabularModel {
+            entries: Vec::new(),
+            index: HashMap::new(),
+            states: HashSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_state(&self, state: &S) -> bool {
+        self.states.contains(state)
+    }
+
+    pub fn update(&mut self, state: S, action: A, next_state: S, reward: i32, time: usize) {
+        let pair = (state, action);
+        match self.index.get(&pair) {
+            Some(&i) => self.entries[i] = (pair, (next_state, reward), time),
+            None => {
+                self.states.insert(pair.0.clone());
+                self.index.insert(pair.clone(), self.entries.len());
+                self.entries.push((pair, (next_state, reward), time));
+            }
+        }
+    }
+
+    pub fn outcome(&self, state: &S, action: &A) -> Option<&(S, i32)> {
+        self.index
+            .get(&(state.clone(), action.clone()))
+            .map(|&i| &self.entries[i].1)
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Option<&ModelEntry<S, A>> {
+        self.entries.choose(rng)
+    }
+}
+
+impl<S, A> Default for TabularModel<S, A>
+where
+    S: State,
+    A: Action,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tabular Dyna-Q (Section 8.2), every real step updates Q, records the transition in the model
+/// and then performs `params.planning_steps` Q-learning updates on transitions drawn from it.
+pub fn dyna_q<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &DynaParameters,
+) -> DynaResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let params = DynaParameters {
+        kappa: 0.0,
+        ..params.clone()
+    };
+    dyna(env, init_vals, init_states, &params)
+}
+
+/// Dyna-Q+ (Section 8.3), planning rewards get a bonus of κ√τ where τ is the number of real
+/// steps since the pair was last tried, so long untried actions are eventually explored again.
+/// As in the book, actions never tried from a visited state are planned as leading back to
+/// the same state with no reward.
+pub fn dyna_q_plus<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &DynaParameters,
+) -> DynaResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    dyna(env, init_vals, init_states, params)
+}
+
+fn dyna<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &DynaParameters,
+) -> DynaResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut model = TabularModel::new();
+    let mut cumulative_rewards = Vec::with_capacity(params.steps);
+    let mut lengths = Vec::new();
+    let mut total = 0.0;
+    let mut state = init_states.choose(&mut rng).unwrap().clone();
+    let mut episode_length = 0;
+    for time in 0..params.steps {
+        if params.kappa > 0.0 && !model.contains_state(&state) {
+            for action in env.posible_actions(&state) {
+                model.update(state.clone(), action, state.clone(), 0, time);
+            }
+        }
+        let action = epsilon_greedy(env, &vals, &state, params.epsilon);
+        let (next_state, reward) = env.response(&state, &action);
+        total += reward as f32;
+        cumulative_rewards.push(total);
+        episode_length += 1;
+
+        q_update(
+            env,
+            &mut vals,
+            &state,
+            &action,
+            &next_state,
+            reward as f32,
+            params,
+        );
+        model.update(state.clone(), action, next_state.clone(), reward, time);
+        for _ in 0..params.planning_steps {
+            let ((s, a), (s_next, r), last) = model.sample(&mut rng).unwrap();
+            let bonus = params.kappa * ((time - last) as f32).sqrt();
+            q_update(env, &mut vals, s, a, s_next, *r as f32 + bonus, params);
+        }
+
+        state = if env.is_terminal(&next_state) {
+            lengths.push(episode_length);
+            episode_length = 0;
+            init_states.choose(&mut rng).unwrap().clone()
+        } else {
+            next_state
+        };
+    }
+    let policy = greedy_policy(env, &vals);
+    DynaResult {
+        action_values: vals,
+        policy,
+        cumulative_rewards,
+        lengths,
+    }
+}
+
+fn q_update<'a, E, S, A>(
+    env: &E,
+    vals: &mut HashMap<(S, A), f32>,
+    state: &S,
+    action: &A,
+    next_state: &S,
+    reward: f32,
+    params: &DynaParameters,
+) where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let next_value = if env.is_terminal(next_state) {
+        0.0
+    } else {
+        max_action_value(env, vals, next_state)
+    };
+    let value = action_value(vals, state, action);
+    vals.insert(
+        (state.clone(), action.clone()),
+        value + params.alpha * (reward + params.gamma * next_value - value),
+    );
+}
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::td_control::{
+    action_value, epsilon_greedy, greedy_policy, max_action_value, TdControlResult,
+};
+use super::temporal_difference::rms_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceKind {
+    Accumulating,
+    Replacing,
+    /// Tabular form of the dutch trace, needs the step size to mark a visit.
+    Dutch,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceParameters {
+    pub alpha: f32,
+    pub gamma: f32,
+    pub lambda: f32,
+    pub epsilon: f32,
+    pub episodes: u32,
+    pub max_steps: usize,
+    pub trace: TraceKind,
+}
+
+impl Default for TraceParameters {
+    fn default() -> Self {
+        TraceParameters {
+            alpha: 0.1,
+            gamma: 1.0,
+            lambda: 0.9,
+            epsilon: 0.1,
+            episodes: 500,
+            max_steps: 100_000,
+            trace: TraceKind::Accumulating,
+        }
+    }
+}
+
+/// Traces below this are dropped so the map only holds recently visited entries.
+const TRACE_CUTOFF: f32 = 1e-4;
+
+/// Decays every trace by `decay` and marks `key` as visited.
+pub fn update_traces<K>(
+    traces: &mut HashMap<K, f32>,
+    key: &K,
+    decay: f32,
+    kind: TraceKind,
+    alpha: f32,
+) where
+    K: Eq + Hash + Clone,
+{
+    let old = traces.get(key).copied().unwrap_or(0.0);
+    for z in traces.values_mut() {
+        *z *= decay;
+    }
+    traces.retain(|_, z| *z > TRACE_CUTOFF);
+    let z = match kind {
+        TraceKind::Accumulating => decay * old + 1.0,
+        TraceKind::Replacing => 1.0,
+        TraceKind::Dutch => (1.0 - alpha) * decay * old + 1.0,
+    };
+    traces.insert(key.clone(), z);
+}
+
+pub fn apply_traces<K>(values: &mut HashMap<K, f32>, traces: &HashMap<K, f32>, step: f32)
+where
+    K: Eq + Hash + Clone,
+{
+    for (key, z) in traces {
+        *values.entry(key.clone()).or_insert(0.0) += step * z;
+    }
+}
+
+/// Tabular TD(λ) prediction with the backward view (Section 12.2).
+pub fn td_lambda<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_values: HashMap<S, f32>,
+    init_states: &[S],
+    env: &E,
+    params: &TraceParameters,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values = init_values;
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut traces: HashMap<S, f32> = HashMap::new();
+        let mut steps = 0;
+        while !env.is_terminal(&state) && steps < params.max_steps {
+            let action = pol.sample_action(&state);
+            let (next_state, reward) = env.response(&state, &action);
+            steps += 1;
+            let decay = params.gamma * params.lambda;
+            update_traces(&mut traces, &state, decay, params.trace, params.alpha);
+            let delta = reward as f32 + params.gamma * state_value(env, &values, &next_state)
+                - values.get(&state).copied().unwrap_or(0.0);
+            apply_traces(&mut values, &traces, params.alpha * delta);
+            state = next_state;
+        }
+    }
+    values
+}
+
+/// True online TD(λ) (Section 12.5) with one-hot features, `params.trace` is ignored since the
+/// algorithm always uses dutch traces.
+pub fn true_online_td_lambda<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_values: HashMap<S, f32>,
+    init_states: &[S],
+    env: &E,
+    params: &TraceParameters,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values = init_values;
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut traces: HashMap<S, f32> = HashMap::new();
+        let mut old_value = 0.0;
+        let mut steps = 0;
+        while !env.is_terminal(&state) && steps < params.max_steps {
+            let action = pol.sample_action(&state);
+            let (next_state, reward) = env.response(&state, &action);
+            steps += 1;
+            let value = values.get(&state).copied().unwrap_or(0.0);
+            let next_value = state_value(env, &values, &next_state);
+            let delta = reward as f32 + params.gamma * next_value - value;
+            let decay = params.gamma * params.lambda;
+            update_traces(&mut traces, &state, decay, TraceKind::Dutch, params.alpha);
+            apply_traces(
+                &mut values,
+                &traces,
+                params.alpha * (delta + value - old_value),
+            );
+            *values.entry(state).or_insert(0.0) -= params.alpha * (value - old_value);
+            old_value = next_value;
+            state = next_state;
+        }
+    }
+    values
+}
+
+fn state_value<'a, E, S, A>(env: &E, values: &HashMap<S, f32>, state: &S) -> f32
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    if env.is_terminal(state) {
+        0.0
+    } else {
+        values.get(state).copied().unwrap_or(0.0)
+    }
+}
+
+/// Sarsa(λ) (Section 12.7) with ε-greedy behavior.
+pub fn sarsa_lambda<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    traced_control(env, init_vals, init_states, params, TracedControl::Sarsa)
+}
+
+/// Watkins's Q(λ) (Section 12.10), traces are cut whenever an exploratory action is taken.
+pub fn watkins_q_lambda<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    traced_control(env, init_vals, init_states, params, TracedControl::WatkinsQ)
+}
+
+/// True online Sarsa(λ) with one-hot state-action features, `params.trace` is ignored.
+pub fn true_online_sarsa_lambda<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    traced_control(
+        env,
+        init_vals,
+        init_states,
+        params,
+        TracedControl::TrueOnline,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TracedControl {
+    Sarsa,
+    WatkinsQ,
+    TrueOnline,
+}
+
+fn traced_control<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+    method: TracedControl,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut returns = Vec::new();
+    let mut lengths = Vec::new();
+    let decay = params.gamma * params.lambda;
+    let kind = match method {
+        TracedControl::TrueOnline => TraceKind::Dutch,
+        _ => params.trace,
+    };
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut action = epsilon_greedy(env, &vals, &state, params.epsilon);
+        let mut traces: HashMap<(S, A), f32> = HashMap::new();
+        let mut old_value = 0.0;
+        let mut total = 0.0;
+        let mut steps = 0;
+        while !env.is_terminal(&state) && steps < params.max_steps {
+            let (next_state, reward) = env.response(&state, &action);
+            total += reward as f32;
+            steps += 1;
+            let pair = (state, action);
+            let value = action_value(&vals, &pair.0, &pair.1);
+            update_traces(&mut traces, &pair, decay, kind, params.alpha);
+
+            let terminal = env.is_terminal(&next_state);
+            let next_action = if terminal {
+                None
+            } else {
+                Some(epsilon_greedy(env, &vals, &next_state, params.epsilon))
+            };
+            let next_value = match (&next_action, method) {
+                (None, _) => 0.0,
+                (Some(_), TracedControl::WatkinsQ) => max_action_value(env, &vals, &next_state),
+                (Some(a), _) => action_value(&vals, &next_state, a),
+            };
+            let delta = reward as f32 + params.gamma * next_value - value;
+            if method == TracedControl::TrueOnline {
+                apply_traces(
+                    &mut vals,
+                    &traces,
+                    params.alpha * (delta + value - old_value),
+                );
+                *vals.entry(pair).or_insert(0.0) -= params.alpha * (value - old_value);
+                old_value = next_value;
+            } else {
+                apply_traces(&mut vals, &traces, params.alpha * delta);
+            }
+            if method == TracedControl::WatkinsQ {
+                if let Some(a) = &next_action {
+                    if action_value(&vals, &next_state, a)
+                        < max_action_value(env, &vals, &next_state)
+                    {
+                        traces.clear();
+                    }
+                }
+            }
+            match next_action {
+                Some(a) => {
+                    state = next_state;
+                    action = a;
+                }
+                None => break,
+            }
+        }
+        returns.push(total);
+        lengths.push(steps);
+    }
+    let policy = greedy_policy(env, &vals);
+    TdControlResult {
+        action_values: vals,
+        policy,
+        returns,
+        lengths,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LambdaMethod {
+    TdLambda(TraceKind),
+    TrueOnline,
+}
+
+/// RMS error against `reference` for every combination of `lambdas` and `alphas`, averaged over
+/// the first `params.episodes` episodes and `runs` runs (as in Figures 12.6 and 12.8). Values
+/// start at zero.
+#[allow(clippy::too_many_arguments)]
+pub fn lambda_sweep<'a, E, S, A>(
+    env: &E,
+    pol: &Policy<'a, S, A>,
+    init_states: &[S],
+    reference: &HashMap<S, f32>,
+    lambdas: &[f32],
+    alphas: &[f32],
+    runs: u32,
+    params: &TraceParameters,
+    method: LambdaMethod,
+) -> Vec<(f32, f32, f32)>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let states: Vec<S> = reference.keys().cloned().collect();
+    let mut points = Vec::new();
+    for &lambda in lambdas {
+        for &alpha in alphas {
+            let mut step_params = params.clone();
+            step_params.lambda = lambda;
+            step_params.alpha = alpha;
+            step_params.episodes = 1;
+            if let LambdaMethod::TdLambda(kind) = method {
+                step_params.trace = kind;
+            }
+            let mut total = 0.0;
+            for _ in 0..runs {
+                let mut values = HashMap::new();
+                for _ in 0..params.episodes {
+                    values = match method {
+                        LambdaMethod::TdLambda(_) => {
+                            td_lambda(pol, values, init_states, env, &step_params)
+                        }
+                        LambdaMethod::TrueOnline => {
+                            true_online_td_lambda(pol, values, init_states, env, &step_params)
+                        }
+                    };
+                    total += rms_error(&values, reference, &states);
+                }
+            }
+            points.push((lambda, alpha, total / (params.episodes * runs) as f32));
+        }
+    }
+    points
+}
+
+/// Mean return per episode of Sarsa(λ) for every λ, averaged over `runs` runs.
+pub fn sarsa_lambda_sweep<'a, E, S, A>(
+    env: &E,
+    init_vals: &HashMap<(S, A), f32>,
+    init_states: &[S],
+    lambdas: &[f32],
+    runs: u32,
+    params: &TraceParameters,
+) -> Vec<(f32, f32)>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    lambdas
+        .iter()
+        .map(|&lambda| {
+            let mut run_params = params.clone();
+            run_params.lambda = lambda;
+            let mut total = 0.0;
+            for _ in 0..runs {
+                let result = sarsa_lambda(env, init_vals.clone(), init_states, &run_params);
+                total += result.returns.iter().sum::<f32>() / result.returns.len() as f32;
+            }
+            (lambda, total / runs as f32)
+        })
+        .collect()
+}
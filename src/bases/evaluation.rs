@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::thread;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+use crate::utils::stats::{confidence_interval, mean, standard_error};
+
+pub struct EvaluationSettings {
+    pub episodes: usize,
+    pub max_steps: usize,
+    pub gamma: f32,
+    pub confidence: f32,
+    pub threads: usize,
+}
+
+impl Default for EvaluationSettings {
+    fn default() -> Self {
+        EvaluationSettings {
+            episodes: 1000,
+            max_steps: 10_000,
+            gamma: 1.0,
+            confidence: 0.95,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyEvaluation {
+    pub returns: Vec<f32>,
+    pub lengths: Vec<usize>,
+    pub mean_return: f32,
+    pub standard_error: f32,
+    pub confidence_interval: (f32, f32),
+    /// Fraction of rollouts that reached a terminal state within the step limit.
+    pub success_rate: f32,
+}
+
+impl PolicyEvaluation {
+    pub fn mean_length(&self) -> f32 {
+        let lengths: Vec<f32> = self.lengths.iter().map(|&l| l as f32).collect();
+        mean(&lengths)
+    }
+
+    /// Episode length below which a fraction `q` of the rollouts fall.
+    pub fn length_quantile(&self, q: f32) -> usize {
+        assert!(
+            !self.lengths.is_empty(),
+            "no episodes to take a quantile of"
+        );
+        let mut lengths = self.lengths.clone();
+        lengths.sort();
+        let index = ((lengths.len() - 1) as f32 * q.clamp(0.0, 1.0)).round() as usize;
+        lengths[index]
+    }
+
+    pub fn length_histogram(&self) -> BTreeMap<usize, u32> {
+        let mut histogram = BTreeMap::new();
+        for length in &self.lengths {
+            *histogram.entry(*length).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+/// Runs one episode of at most `max_steps` transitions and returns its discounted return,
+/// its length and whether it ended in a terminal state.
+pub fn rollout<'a, E, S, A>(
+    env: &E,
+    init_state: &S,
+    pol: &Policy<'a, S, A>,
+    max_steps: usize,
+    gamma: f32,
+) -> (f32, usize, bool)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let (trajectory, terminated) = env.episode_with_limit(init_state, pol, max_steps);
+    let mut g = 0.0;
+    for (_, _, reward) in trajectory.iter().rev() {
+        g = gamma * g + *reward as f32;
+    }
+    (g, trajectory.len(), terminated)
+}
+
+/// Estimates the performance of `pol` from `settings.episodes` rollouts, the start states are
+/// cycled through in order so each of them gets the same share of episodes.
+pub fn evaluate_policy<'a, E, S, A>(
+    env: &E,
+    pol: &Policy<'a, S, A>,
+    init_states: &[S],
+    settings: &EvaluationSettings,
+) -> PolicyEvaluation
+where
+    S: State + Sync,
+    A: Action + Sync,
+    E: Enviorment<'a, S, A> + Sync,
+{
+    assert!(
+        !init_states.is_empty(),
+        "evaluate_policy needs a start state"
+    );
+    let run = |episodes: std::ops::Range<usize>| {
+        episodes
+            .map(|i| {
+                let init_state = &init_states[i % init_states.len()];
+                rollout(env, init_state, pol, settings.max_steps, settings.gamma)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let threads = settings.threads.max(1);
+    let results = if threads == 1 {
+        run(0..settings.episodes)
+    } else {
+        let chunk = settings.episodes.div_ceil(threads);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|t| {
+                    let start = (t * chunk).min(settings.episodes);
+                    let end = ((t + 1) * chunk).min(settings.episodes);
+                    scope.spawn(move || run(start..end))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    };
+
+    let returns: Vec<f32> = results.iter().map(|(g, _, _)| *g).collect();
+    let lengths: Vec<usize> = results.iter().map(|(_, l, _)| *l).collect();
+    let successes = results.iter().filter(|(_, _, done)| *done).count();
+    PolicyEvaluation {
+        mean_return: mean(&returns),
+        standard_error: standard_error(&returns),
+        confidence_interval: confidence_interval(&returns, settings.confidence),
+        success_rate: successes as f32 / results.len().max(1) as f32,
+        returns,
+        lengths,
+    }
+}
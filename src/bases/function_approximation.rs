@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use super::mdp::{Action, State};
+
+/// Real valued view of a state, what the coarse coding feature families work on.
+pub trait StateVector {
+    fn to_vector(&self) -> Vec<f64>;
+}
+
+/// Maps a state to a feature vector x(s) of fixed length.
+pub trait FeatureExtractor<S> {
+    fn features(&self, state: &S) -> Vec<f32>;
+    fn dimension(&self) -> usize;
+}
+
+/// Parametric approximation v̂(s, w) of a state value function, seen through the features of the
+/// state.
+pub trait ValueFunction {
+    fn value(&self, features: &[f32]) -> f32;
+    /// ∇v̂ with respect to the parameters, flattened.
+    fn gradient(&self, features: &[f32]) -> Vec<f32>;
+    /// w ← w + step ∇v̂, the semi-gradient update with step = α (U − v̂).
+    fn update(&mut self, features: &[f32], step: f32);
+}
+
+/// Parametric approximation q̂(s, a, w) of an action value function.
+pub trait ActionValueFunction<A> {
+    fn value(&self, features: &[f32], action: &A) -> f32;
+    /// ∇q̂ with respect to the parameters that `update` moves for `action`, flattened. That is
+    /// every parameter when they are shared between actions, and only the block of `action`
+    /// when each action has its own.
+    fn gradient(&self, features: &[f32], action: &A) -> Vec<f32>;
+    /// w ← w + step ∇q̂, the semi-gradient update with step = α (U − q̂).
+    fn update(&mut self, features: &[f32], action: &A, step: f32);
+}
+
+/// Parametric policy π(a|s, θ) over the actions available in a state (Chapter 13).
+pub trait PolicyApproximation<A> {
+    /// π(a|s, θ) of every action of `actions`, in the same order.
+    fn probabilities(&self, features: &[f32], actions: &[A]) -> Vec<f32>;
+    /// ∇ ln π(a|s, θ) with respect to the parameters, flattened.
+    fn log_gradient(&self, features: &[f32], actions: &[A], action: &A) -> Vec<f32>;
+    /// θ ← θ + step ∇ ln π(a|s, θ), the policy gradient update with step = α γᵗ G (Eq. 13.8).
+    fn update(&mut self, features: &[f32], actions: &[A], action: &A, step: f32);
+}
+
+pub fn dot(x: &[f32], y: &[f32]) -> f32 {
+    x.iter().zip(y).map(|(a, b)| a * b).sum()
+}
+
+/// Linear approximation v̂(s, w) = wᵀx(s), whose gradient is the feature vector (Section 9.4).
+#[derive(Debug, Clone)]
+pub struct Linear {
+    pub weights: Vec<f32>,
+}
+
+impl Linear {
+    pub fn new(dimension: usize) -> Self {
+        Linear {
+            weights: vec![0.0; dimension],
+        }
+    }
+}
+
+impl ValueFunction for Linear {
+    fn value(&self, features: &[f32]) -> f32 {
+        dot(&self.weights, features)
+    }
+    fn gradient(&self, features: &[f32]) -> Vec<f32> {
+        features.to_vec()
+    }
+    fn update(&mut self, features: &[f32], step: f32) {
+        for (w, x) in self.weights.iter_mut().zip(features) {
+            *w += step * x;
+        }
+    }
+}
+
+/// Linear action values with a separate weight vector per action, equivalent to stacking the
+/// state features into the block of the action and zeros everywhere else.
+#[derive(Debug, Clone)]
+pub struct LinearActionValues<A>
+where
+    A: Action,
+{
+    pub dimension: usize,
+    pub weights: HashMap<A, Vec<f32>>,
+}
+
+impl<A> LinearActionValues<A>
+where
+    A: Action,
+{
+    pub fn new(dimension: usize) -> Self {
+        LinearActionValues {
+            dimension,
+            weights: HashMap::new(),
+        }
+    }
+}
+
+impl<A> ActionValueFunction<A> for LinearActionValues<A>
+where
+    A: Action,
+{
+    fn value(&self, features: &[f32], action: &A) -> f32 {
+        self.weights
+            .get(action)
+            .map_or(0.0, |weights| dot(weights, features))
+    }
+    /// The block of `action`, which is just the state features.
+    fn gradient(&self, features: &[f32], _action: &A) -> Vec<f32> {
+        features.to_vec()
+    }
+    fn update(&mut self, features: &[f32], action: &A, step: f32) {
+        let weights = self
+            .weights
+            .entry(action.clone())
+            .or_insert_with(|| vec![0.0; self.dimension]);
+        for (w, x) in weights.iter_mut().zip(features) {
+            *w += step * x;
+        }
+    }
+}
+
+/// One-hot features, one per listed state, with which the linear methods reduce to the tabular
+/// ones. Unlisted states get all zeros.
+#[derive(Debug, Clone)]
+pub struct TabularFeatures<S>
+where
+    S: State,
+{
+    index: HashMap<S, usize>,
+}
+
+impl<S> TabularFeatures<S>
+where
+    S: State,
+{
+    pub fn new(states: &[S]) -> Self {
+        TabularFeatures {
+            index: states
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (s.clone(), i))
+                .collect(),
+        }
+    }
+}
+
+impl<S> FeatureExtractor<S> for TabularFeatures<S>
+where
+    S: State,
+{
+    fn features(&self, state: &S) -> Vec<f32> {
+        let mut x = vec![0.0; self.index.len()];
+        if let Some(&i) = self.index.get(state) {
+            x[i] = 1.0;
+        }
+        x
+    }
+    fn dimension(&self) -> usize {
+        self.index.len()
+    }
+}
+
+/// Root of the mean squared value error VE (Eq. 9.1) of the approximation against `reference`,
+/// weighted by the on-policy distribution μ when given and uniformly otherwise.
+pub fn value_error<S, F, V>(
+    approximation: &V,
+    features: &F,
+    reference: &HashMap<S, f32>,
+    distribution: Option<&HashMap<S, f32>>,
+) -> f32
+where
+    S: State,
+    F: FeatureExtractor<S> + ?Sized,
+    V: ValueFunction,
+{
+    let mut total = 0.0;
+    let mut weights = 0.0;
+    for (state, v) in reference {
+        let mu = distribution.map_or(1.0, |d| d.get(state).copied().unwrap_or(0.0));
+        let error = approximation.value(&features.features(state)) - v;
+        total += mu * error * error;
+        weights += mu;
+    }
+    if weights == 0.0 {
+        return 0.0;
+    }
+    (total / weights).sqrt()
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use super::function_approximation::{dot, FeatureExtractor, Linear};
+use super::mdp::{Action, Enviorment, EnviormentModel, Policy, State};
+use crate::utils::linear_algebra::Matrix;
+
+/// Off-policy prediction methods for linear v̂(s, w) = wᵀx(s) (Chapter 11).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffPolicyMethod {
+    /// Semi-gradient off-policy TD(0) (Eq. 11.2), which can diverge.
+    SemiGradient,
+    /// GTD2 (Section 11.7), a gradient step on the projected Bellman error through the
+    /// secondary weights v.
+    Gtd2,
+    /// TD(0) with gradient correction (Section 11.7).
+    Tdc,
+    /// Emphatic TD(λ) (Sections 11.8 and 12.11), semi-gradient TD reweighted by the followon
+    /// trace of the target policy.
+    Emphatic,
+}
+
+#[derive(Debug, Clone)]
+pub struct GradientTdParameters {
+    pub alpha: f32,
+    /// Step size of the secondary weights of GTD2 and TDC.
+    pub beta: f32,
+    pub gamma: f32,
+    /// Trace decay of emphatic TD(λ), unused by the other methods.
+    pub lambda: f32,
+    /// Transitions of the sampled methods or sweeps of the expected ones.
+    pub steps: usize,
+    /// The weights are recorded every this many steps.
+    pub record_every: usize,
+}
+
+impl Default for GradientTdParameters {
+    fn default() -> Self {
+        GradientTdParameters {
+            alpha: 0.005,
+            beta: 0.05,
+            gamma: 0.99,
+            lambda: 0.0,
+            steps: 1000,
+            record_every: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GradientTdResult {
+    pub approximation: Linear,
+    /// Weights before any update and then every `record_every` steps.
+    pub history: Vec<Vec<f32>>,
+}
+
+fn ratio<'a, S, A>(behavior: &Policy<'a, S, A>, target: &Policy<'a, S, A>, s: &S, a: &A) -> f32
+where
+    S: State,
+    A: Action,
+{
+    target.probability(s, a) / behavior.probability(s, a)
+}
+
+/// Off-policy linear TD along one stream of experience of `behavior` that starts in
+/// `init_state` and starts over whenever it reaches a terminal state, learning the values of
+/// `target` with every state of interest one. Both policies must give probabilities for every
+/// visited state.
+#[allow(clippy::too_many_arguments)]
+pub fn off_policy_linear_td<'a, E, S, A, F>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    target: &Policy<'a, S, A>,
+    features: &F,
+    weights: Vec<f32>,
+    init_state: &S,
+    method: OffPolicyMethod,
+    params: &GradientTdParameters,
+) -> GradientTdResult
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    assert!(params.record_every > 0, "record_every must be at least one");
+    let dimension = weights.len();
+    let mut w = weights;
+    let mut v = vec![0.0; dimension];
+    let mut trace = vec![0.0; dimension];
+    let mut followon = 0.0;
+    let mut last_rho = 0.0;
+    let mut history = vec![w.clone()];
+    let mut state = init_state.clone();
+    let mut x = features.features(&state);
+    for step in 0..params.steps {
+        let action = behavior.sample_action(&state);
+        let (next_state, reward) = env.response(&state, &action);
+        let rho = ratio(behavior, target, &state, &action);
+        let terminal = env.is_terminal(&next_state);
+        let next_x = if terminal {
+            vec![0.0; dimension]
+        } else {
+            features.features(&next_state)
+        };
+        let delta = reward as f32 + params.gamma * dot(&w, &next_x) - dot(&w, &x);
+        match method {
+            OffPolicyMethod::SemiGradient => {
+                for (w_i, x_i) in w.iter_mut().zip(&x) {
+                    *w_i += params.alpha * rho * delta * x_i;
+                }
+            }
+            OffPolicyMethod::Gtd2 | OffPolicyMethod::Tdc => {
+                let prediction = dot(&v, &x);
+                for i in 0..dimension {
+                    w[i] += params.alpha
+                        * rho
+                        * match method {
+                            OffPolicyMethod::Gtd2 => (x[i] - params.gamma * next_x[i]) * prediction,
+                            _ => delta * x[i] - params.gamma * next_x[i] * prediction,
+                        };
+                    v[i] += params.beta * (rho * delta - prediction) * x[i];
+                }
+            }
+            OffPolicyMethod::Emphatic => {
+                followon = last_rho * params.gamma * followon + 1.0;
+                let emphasis = params.lambda + (1.0 - params.lambda) * followon;
+                for (z, x_i) in trace.iter_mut().zip(&x) {
+                    *z = rho * (params.gamma * params.lambda * *z + emphasis * x_i);
+                }
+                for (w_i, z) in w.iter_mut().zip(&trace) {
+                    *w_i += params.alpha * delta * z;
+                }
+                last_rho = rho;
+            }
+        }
+        if terminal {
+            state = init_state.clone();
+            x = features.features(&state);
+            trace.iter_mut().for_each(|z| *z = 0.0);
+            followon = 0.0;
+            last_rho = 0.0;
+        } else {
+            state = next_state;
+            x = next_x;
+        }
+        if (step + 1) % params.record_every == 0 {
+            history.push(w.clone());
+        }
+    }
+    GradientTdResult {
+        approximation: Linear { weights: w },
+        history,
+    }
+}
+
+/// E_π[R + γ x(S') | s] of every state, with x(S') zero for terminal next states.
+fn expected_targets<'a, E, S, A, F>(
+    env: &E,
+    target: &Policy<'a, S, A>,
+    features: &F,
+    states: &[S],
+) -> Vec<(f32, Vec<f32>)>
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    let dimension = features.dimension();
+    states
+        .iter()
+        .map(|state| {
+            let mut reward = 0.0;
+            let mut next_x = vec![0.0; dimension];
+            for action in env.posible_actions(state) {
+                let pi = target.probability(state, &action);
+                if pi == 0.0 {
+                    continue;
+                }
+                for ((next_state, r), prob) in env.dynamics(state, &action) {
+                    reward += pi * prob * r as f32;
+                    if !env.is_terminal(&next_state) {
+                        for (n, f) in next_x.iter_mut().zip(features.features(&next_state)) {
+                            *n += pi * prob * f;
+                        }
+                    }
+                }
+            }
+            (reward, next_x)
+        })
+        .collect()
+}
+
+/// Expected emphasis m(s) = λ d_μ(s) + (1 − λ) f(s) of every state, where the followon
+/// f = d_μ + γ P_πᵀ f is solved directly, which has a unique solution for γ < 1.
+fn expected_emphasis<'a, E, S, A>(
+    env: &E,
+    target: &Policy<'a, S, A>,
+    states: &[S],
+    distribution: &[f32],
+    gamma: f32,
+    lambda: f32,
+) -> Vec<f32>
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    let n = states.len();
+    let index: HashMap<&S, usize> = states.iter().enumerate().map(|(i, s)| (s, i)).collect();
+    let mut system = Matrix::identity(n);
+    for (i, state) in states.iter().enumerate() {
+        for action in env.posible_actions(state) {
+            let pi = target.probability(state, &action);
+            for ((next_state, _), prob) in env.dynamics(state, &action) {
+                if let Some(&j) = index.get(&next_state) {
+                    let entry = system.get(j, i) - (gamma * pi * prob) as f64;
+                    system.set(j, i, entry);
+                }
+            }
+        }
+    }
+    let mu: Vec<f64> = distribution.iter().map(|&d| d as f64).collect();
+    let followon = system
+        .solve(&mu)
+        .expect("I − γ P_πᵀ is invertible for γ < 1");
+    followon
+        .iter()
+        .zip(distribution)
+        .map(|(&f, &d)| lambda * d + (1.0 - lambda) * f as f32)
+        .collect()
+}
+
+/// Expected version of `off_policy_linear_td` (Figures 11.2, 11.5 and 11.6), every sweep makes the
+/// update of the method in expectation over the states distributed by `distribution` (the
+/// behavior's μ) and the target's actions, with the known dynamics. The semi-gradient method is
+/// semi-gradient DP when μ is uniform. Emphatic TD is only expected for λ = 0 and γ < 1.
+#[allow(clippy::too_many_arguments)]
+pub fn expected_off_policy_td<'a, E, S, A, F>(
+    env: &E,
+    target: &Policy<'a, S, A>,
+    features: &F,
+    weights: Vec<f32>,
+    states: &[S],
+    distribution: &[f32],
+    method: OffPolicyMethod,
+    params: &GradientTdParameters,
+) -> GradientTdResult
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    assert!(params.record_every > 0, "record_every must be at least one");
+    assert!(
+        method != OffPolicyMethod::Emphatic || params.gamma < 1.0,
+        "the expected emphasis needs γ < 1"
+    );
+    let dimension = weights.len();
+    let xs: Vec<Vec<f32>> = states.iter().map(|s| features.features(s)).collect();
+    let targets = expected_targets(env, target, features, states);
+    let emphasis = match method {
+        OffPolicyMethod::Emphatic => {
+            expected_emphasis(env, target, states, distribution, params.gamma, 0.0)
+        }
+        _ => distribution.to_vec(),
+    };
+    let mut w = weights;
+    let mut v = vec![0.0; dimension];
+    let mut history = vec![w.clone()];
+    for sweep in 0..params.steps {
+        let mut w_step = vec![0.0; dimension];
+        let mut v_step = vec![0.0; dimension];
+        for (i, (x, (reward, next_x))) in xs.iter().zip(&targets).enumerate() {
+            let delta = reward + params.gamma * dot(&w, next_x) - dot(&w, x);
+            let prediction = dot(&v, x);
+            for k in 0..dimension {
+                w_step[k] += emphasis[i]
+                    * match method {
+                        OffPolicyMethod::SemiGradient | OffPolicyMethod::Emphatic => delta * x[k],
+                        OffPolicyMethod::Gtd2 => (x[k] - params.gamma * next_x[k]) * prediction,
+                        OffPolicyMethod::Tdc => {
+                            delta * x[k] - params.gamma * next_x[k] * prediction
+                        }
+                    };
+                v_step[k] += distribution[i] * (delta - prediction) * x[k];
+            }
+        }
+        for k in 0..dimension {
+            w[k] += params.alpha * w_step[k];
+            v[k] += params.beta * v_step[k];
+        }
+        if (sweep + 1) % params.record_every == 0 {
+            history.push(w.clone());
+        }
+    }
+    GradientTdResult {
+        approximation: Linear { weights: w },
+        history,
+    }
+}
+
+/// Root of the projected Bellman error ‖Πδ̄_w‖²_μ (Section 11.4) of the linear weights under
+/// `target`, with the projection Π weighted by `distribution` over `states`.
+pub fn projected_bellman_error<'a, E, S, A, F>(
+    env: &E,
+    target: &Policy<'a, S, A>,
+    features: &F,
+    weights: &[f32],
+    states: &[S],
+    distribution: &[f32],
+    gamma: f32,
+) -> f32
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    let dimension = weights.len();
+    let targets = expected_targets(env, target, features, states);
+    let mut gram = Matrix::zeros(dimension, dimension);
+    let mut correlation = vec![0.0; dimension];
+    for ((state, (reward, next_x)), &mu) in states.iter().zip(&targets).zip(distribution) {
+        let state_x = features.features(state);
+        let x: Vec<f64> = state_x.iter().map(|&f| f as f64).collect();
+        let delta = reward + gamma * dot(weights, next_x) - dot(weights, &state_x);
+        gram.add_outer(&x, &x, mu as f64);
+        for (c, x_i) in correlation.iter_mut().zip(&x) {
+            *c += (mu * delta) as f64 * x_i;
+        }
+    }
+    // Features need not be independent over the states, the ridge picks one solution.
+    gram.add_diagonal(1e-9);
+    let Some(solution) = gram.solve(&correlation) else {
+        return f32::NAN;
+    };
+    let error: f64 = solution.iter().zip(&correlation).map(|(s, c)| s * c).sum();
+    error.max(0.0).sqrt() as f32
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use super::function_approximation::{
+    ActionValueFunction, FeatureExtractor, Linear, LinearActionValues,
+};
+use super::mdp::{Action, Enviorment, State, Transition};
+use crate::utils::linear_algebra::Matrix;
+
+#[derive(Debug, Clone)]
+pub struct LeastSquaresSettings {
+    pub gamma: f32,
+    /// Trace decay of LSTD(λ), zero gives plain LSTD.
+    pub lambda: f32,
+    /// ε of the ridge term εI added to A, which keeps it invertible with few samples.
+    pub regularization: f64,
+    /// Policy iterations of LSPI.
+    pub max_iterations: usize,
+    /// LSPI stops once no weight changes by more than this.
+    pub tolerance: f64,
+}
+
+impl Default for LeastSquaresSettings {
+    fn default() -> Self {
+        LeastSquaresSettings {
+            gamma: 1.0,
+            lambda: 0.0,
+            regularization: 1e-3,
+            max_iterations: 20,
+            tolerance: 1e-4,
+        }
+    }
+}
+
+fn to_f64(x: &[f32]) -> Vec<f64> {
+    x.iter().map(|&v| v as f64).collect()
+}
+
+/// Features of `state`, all zeros for terminal states so they are worth nothing.
+fn state_features<'a, E, S, A, F>(env: &E, features: &F, state: &S) -> Vec<f64>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    if env.is_terminal(state) {
+        vec![0.0; features.dimension()]
+    } else {
+        to_f64(&features.features(state))
+    }
+}
+
+/// Batch LSTD(λ) (Section 9.8, with the traces of Chapter 12). Accumulates
+/// A = Σ z (x − γx')ᵀ + εI and b = Σ R z over every transition of the recorded episodes, with the
+/// trace z = γλz + x reset at the start of each, and solves A w = b. None when A is singular.
+pub fn lstd<'a, E, S, A, F>(
+    env: &E,
+    episodes: &[Vec<Transition<S, A>>],
+    features: &F,
+    settings: &LeastSquaresSettings,
+) -> Option<Linear>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    let dimension = features.dimension();
+    let gamma = settings.gamma as f64;
+    let decay = gamma * settings.lambda as f64;
+    let mut a = Matrix::identity(dimension);
+    a.scale(settings.regularization);
+    let mut b = vec![0.0; dimension];
+    for episode in episodes {
+        let mut trace = vec![0.0; dimension];
+        for transition in episode {
+            let x = state_features(env, features, &transition.state);
+            let next_x = state_features(env, features, &transition.next_state);
+            for (z, xi) in trace.iter_mut().zip(&x) {
+                *z = decay * *z + xi;
+            }
+            let difference: Vec<f64> = x.iter().zip(&next_x).map(|(x, y)| x - gamma * y).collect();
+            a.add_outer(&trace, &difference, 1.0);
+            for (bi, z) in b.iter_mut().zip(&trace) {
+                *bi += transition.reward as f64 * z;
+            }
+        }
+    }
+    let weights = a.solve(&b)?;
+    Some(Linear {
+        weights: weights.into_iter().map(|w| w as f32).collect(),
+    })
+}
+
+/// Incremental LSTD(λ) keeping A⁻¹ up to date with the Sherman–Morrison formula, O(d²) per step
+/// instead of solving the system again (Section 9.8).
+#[derive(Debug, Clone)]
+pub struct RecursiveLstd {
+    pub gamma: f64,
+    pub lambda: f64,
+    inverse: Matrix,
+    b: Vec<f64>,
+    trace: Vec<f64>,
+}
+
+impl RecursiveLstd {
+    /// A starts as εI, so its inverse starts as I/ε.
+    pub fn new(dimension: usize, settings: &LeastSquaresSettings) -> Self {
+        assert!(
+            settings.regularization > 0.0,
+            "recursive LSTD needs a positive regularization to invert A"
+        );
+        let mut inverse = Matrix::identity(dimension);
+        inverse.scale(1.0 / settings.regularization);
+        RecursiveLstd {
+            gamma: settings.gamma as f64,
+            lambda: settings.lambda as f64,
+            inverse,
+            b: vec![0.0; dimension],
+            trace: vec![0.0; dimension],
+        }
+    }
+
+    /// Adds one transition, `next_features` should be all zeros when the next state is terminal.
+    /// Returns false and leaves the estimate unchanged when the update would make A singular.
+    pub fn observe(&mut self, features: &[f32], reward: i32, next_features: &[f32]) -> bool {
+        let trace: Vec<f64> = self
+            .trace
+            .iter()
+            .zip(features)
+            .map(|(z, x)| self.gamma * self.lambda * z + *x as f64)
+            .collect();
+        let difference: Vec<f64> = features
+            .iter()
+            .zip(next_features)
+            .map(|(x, y)| *x as f64 - self.gamma * *y as f64)
+            .collect();
+        if !self.inverse.sherman_morrison(&trace, &difference) {
+            return false;
+        }
+        for (bi, z) in self.b.iter_mut().zip(&trace) {
+            *bi += reward as f64 * z;
+        }
+        self.trace = trace;
+        true
+    }
+
+    /// Clears the trace, to be called between episodes.
+    pub fn end_episode(&mut self) {
+        self.trace.iter_mut().for_each(|z| *z = 0.0);
+    }
+
+    pub fn weights(&self) -> Linear {
+        Linear {
+            weights: self
+                .inverse
+                .mul_vec(&self.b)
+                .into_iter()
+                .map(|w| w as f32)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LspiResult<A>
+where
+    A: Action,
+{
+    pub action_values: LinearActionValues<A>,
+    /// Policy evaluations performed.
+    pub iterations: usize,
+}
+
+/// First action of `state` among `actions` with the largest value, a deterministic greedy policy
+/// so that LSTDQ evaluates the same policy for every sample. None when the state has none of them.
+fn first_greedy<'a, E, S, A>(
+    env: &E,
+    q: &LinearActionValues<A>,
+    actions: &[A],
+    x: &[f32],
+    state: &S,
+) -> Option<A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut best = None;
+    let mut best_value = f32::NEG_INFINITY;
+    for action in env.posible_actions(state) {
+        if !actions.contains(&action) {
+            continue;
+        }
+        let value = q.value(x, &action);
+        if best.is_none() || value > best_value {
+            best_value = value;
+            best = Some(action);
+        }
+    }
+    best
+}
+
+/// Least-squares policy iteration (Lagoudakis and Parr). LSTDQ evaluates the greedy policy of
+/// the current weights on the recorded transitions, with the state features stacked into one
+/// block per action, and the result becomes the next policy until the weights settle. Actions
+/// are those tried in the data, and a next state where none of them is available counts as
+/// terminal. Returns None when a system is singular.
+pub fn lspi<'a, E, S, A, F>(
+    env: &E,
+    transitions: &[Transition<S, A>],
+    features: &F,
+    settings: &LeastSquaresSettings,
+) -> Option<LspiResult<A>>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+{
+    let dimension = features.dimension();
+    let mut actions: Vec<A> = Vec::new();
+    for transition in transitions {
+        if !actions.contains(&transition.action) {
+            actions.push(transition.action.clone());
+        }
+    }
+    let block: HashMap<&A, usize> = actions.iter().enumerate().map(|(i, a)| (a, i)).collect();
+    let samples: Vec<(Vec<f32>, Option<Vec<f32>>)> = transitions
+        .iter()
+        .map(|t| {
+            let next = (!env.is_terminal(&t.next_state)).then(|| features.features(&t.next_state));
+            (features.features(&t.state), next)
+        })
+        .collect();
+    let stacked = |x: &[f32], action: &A| -> Vec<f64> {
+        let mut phi = vec![0.0; dimension * actions.len()];
+        if let Some(&i) = block.get(action) {
+            for (p, &xi) in phi[i * dimension..(i + 1) * dimension].iter_mut().zip(x) {
+                *p = xi as f64;
+            }
+        }
+        phi
+    };
+
+    let gamma = settings.gamma as f64;
+    let size = dimension * actions.len();
+    let mut q = LinearActionValues::new(dimension);
+    let mut weights = vec![0.0; size];
+    let mut iterations = 0;
+    while iterations < settings.max_iterations {
+        iterations += 1;
+        let mut a = Matrix::identity(size);
+        a.scale(settings.regularization);
+        let mut b = vec![0.0; size];
+        for (transition, (x, next_x)) in transitions.iter().zip(&samples) {
+            let phi = stacked(x, &transition.action);
+            let next_action = next_x.as_ref().and_then(|next_x| {
+                first_greedy(env, &q, &actions, next_x, &transition.next_state)
+                    .map(|action| (next_x, action))
+            });
+            let next_phi = match next_action {
+                Some((next_x, next_action)) => stacked(next_x, &next_action),
+                None => vec![0.0; size],
+            };
+            let difference: Vec<f64> = phi
+                .iter()
+                .zip(&next_phi)
+                .map(|(p, n)| p - gamma * n)
+                .collect();
+            a.add_outer(&phi, &difference, 1.0);
+            for (bi, p) in b.iter_mut().zip(&phi) {
+                *bi += transition.reward as f64 * p;
+            }
+        }
+        let new_weights = a.solve(&b)?;
+        let change = weights
+            .iter()
+            .zip(&new_weights)
+            .map(|(w, n)| (w - n).abs())
+            .fold(0.0, f64::max);
+        weights = new_weights;
+        for (i, action) in actions.iter().enumerate() {
+            let block = weights[i * dimension..(i + 1) * dimension]
+                .iter()
+                .map(|&w| w as f32)
+                .collect();
+            q.weights.insert(action.clone(), block);
+        }
+        if change < settings.tolerance {
+            break;
+        }
+    }
+    Some(LspiResult {
+        action_values: q,
+        iterations,
+    })
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::rollout_planning::planned_episode;
+
+/// How actions are picked once a simulation leaves the tree.
+pub enum RolloutPolicy<'p, 'a, S, A> {
+    Uniform,
+    Policy(&'p Policy<'a, S, A>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+#[derive(Debug, Clone)]
+pub struct MctsSettings {
+    /// Constant c of the UCB1 bonus c √(ln N / n).
+    pub exploration: f32,
+    pub gamma: f32,
+    pub budget: Budget,
+    /// Steps a simulation may spend inside the tree.
+    pub max_depth: usize,
+    /// Steps of every rollout after leaving the tree.
+    pub rollout_depth: usize,
+    /// Keep the statistics between calls to `act`, otherwise every decision starts from scratch.
+    pub reuse_tree: bool,
+}
+
+impl Default for MctsSettings {
+    fn default() -> Self {
+        MctsSettings {
+            exploration: 1.0,
+            gamma: 1.0,
+            budget: Budget::Iterations(1000),
+            max_depth: 100,
+            rollout_depth: 100,
+            reuse_tree: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ActionStats<A> {
+    pub action: A,
+    pub visits: u32,
+    /// Mean return of the simulations that took the action.
+    pub value: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node<A> {
+    pub visits: u32,
+    pub actions: Vec<ActionStats<A>>,
+}
+
+/// UCT (Section 8.11). The tree is a transposition table keyed on the state, so states reached
+/// through different paths share statistics, and it is kept between decisions when
+/// `settings.reuse_tree` is set. Transitions are sampled with `Enviorment::response`.
+pub struct Mcts<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub settings: MctsSettings,
+    pub rollout: RolloutPolicy<'p, 'a, S, A>,
+    pub table: HashMap<S, Node<A>>,
+    /// Simulations run by the last call to `act`.
+    pub iterations: usize,
+}
+
+impl<'p, 'a, S, A> Mcts<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn new(settings: MctsSettings, rollout: RolloutPolicy<'p, 'a, S, A>) -> Self {
+        Mcts {
+            settings,
+            rollout,
+            table: HashMap::new(),
+            iterations: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Runs simulations from `state` until the budget is spent and returns the most visited
+    /// action. The root is expanded first, so with no simulations (a zero budget or depth, or a
+    /// terminal root) one of its actions is picked at random.
+    pub fn act<E>(&mut self, env: &E, state: &S) -> A
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        if !self.settings.reuse_tree {
+            self.table.clear();
+        }
+        if !self.table.contains_key(state) {
+            self.expand(env, state);
+        }
+        assert!(
+            !self.table[state].actions.is_empty(),
+            "MCTS needs at least one action in the root state"
+        );
+        let start = Instant::now();
+        self.iterations = 0;
+        loop {
+            let done = match self.settings.budget {
+                Budget::Iterations(n) => self.iterations >= n,
+                Budget::Time(limit) => self.iterations > 0 && start.elapsed() >= limit,
+            };
+            if done {
+                break;
+            }
+            self.simulate(env, state);
+            self.iterations += 1;
+        }
+        let actions = &self.table[state].actions;
+        let most_visits = actions.iter().map(|stats| stats.visits).max().unwrap();
+        let best: Vec<&ActionStats<A>> = actions
+            .iter()
+            .filter(|stats| stats.visits == most_visits)
+            .collect();
+        best.choose(&mut thread_rng()).unwrap().action.clone()
+    }
+
+    /// Mean simulated return of every action tried in `state`.
+    pub fn action_values(&self, state: &S) -> Vec<(A, f32)> {
+        self.table
+            .get(state)
+            .map(|node| {
+                node.actions
+                    .iter()
+                    .filter(|stats| stats.visits > 0)
+                    .map(|stats| (stats.action.clone(), stats.value))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Selection and expansion of one new node, a rollout from it and the backup of the
+    /// discounted return along the path.
+    fn simulate<E>(&mut self, env: &E, root: &S)
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        let mut rng = thread_rng();
+        let mut path: Vec<(S, usize, i32)> = Vec::new();
+        let mut state = root.clone();
+        let mut tail = None;
+        while !env.is_terminal(&state) && path.len() < self.settings.max_depth {
+            let Some(node) = self.table.get(&state) else {
+                self.expand(env, &state);
+                tail = Some(self.rollout(env, &state));
+                break;
+            };
+            let untried: Vec<usize> = (0..node.actions.len())
+                .filter(|&i| node.actions[i].visits == 0)
+                .collect();
+            let index = match untried.choose(&mut rng) {
+                Some(&i) => i,
+                None => {
+                    let log_visits = (node.visits as f32).ln();
+                    let ucb = |stats: &ActionStats<A>| {
+                        stats.value
+                            + self.settings.exploration * (log_visits / stats.visits as f32).sqrt()
+                    };
+                    (0..node.actions.len())
+                        .max_by(|&i, &j| ucb(&node.actions[i]).total_cmp(&ucb(&node.actions[j])))
+                        .unwrap()
+                }
+            };
+            let (next_state, reward) = env.response(&state, &node.actions[index].action);
+            path.push((state, index, reward));
+            state = next_state;
+        }
+
+        // Cut at max_depth, which also bounds cycles through the table, the rest of the return
+        // is still estimated with a rollout.
+        let mut g = match tail {
+            Some(g) => g,
+            None if env.is_terminal(&state) => 0.0,
+            None => self.rollout(env, &state),
+        };
+        for (state, index, reward) in path.into_iter().rev() {
+            g = reward as f32 + self.settings.gamma * g;
+            let node = self.table.get_mut(&state).unwrap();
+            node.visits += 1;
+            let stats = &mut node.actions[index];
+            stats.visits += 1;
+            stats.value += (g - stats.value) / stats.visits as f32;
+        }
+    }
+
+    fn expand<E>(&mut self, env: &E, state: &S)
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        let actions = env
+            .posible_actions(state)
+            .into_iter()
+            .map(|action| ActionStats {
+                action,
+                visits: 0,
+                value: 0.0,
+            })
+            .collect();
+        self.table
+            .insert(state.clone(), Node { visits: 0, actions });
+    }
+
+    fn rollout<E>(&self, env: &E, state: &S) -> f32
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        let mut rng = thread_rng();
+        let mut state = state.clone();
+        let mut g = 0.0;
+        let mut discount = 1.0;
+        for _ in 0..self.settings.rollout_depth {
+            if env.is_terminal(&state) {
+                break;
+            }
+            let action = match &self.rollout {
+                RolloutPolicy::Uniform => env
+                    .posible_actions(&state)
+                    .choose(&mut rng)
+                    .unwrap()
+                    .clone(),
+                RolloutPolicy::Policy(policy) => policy.sample_action(&state),
+            };
+            let (next_state, reward) = env.response(&state, &action);
+            g += discount * reward as f32;
+            discount *= self.settings.gamma;
+            state = next_state;
+        }
+        g
+    }
+}
+
+/// Plays an episode from `init_state` choosing every action with `mcts`, returns the trajectory
+/// and whether it reached a terminal state within `max_steps`.
+pub fn mcts_episode<'p, 'a, E, S, A>(
+    env: &E,
+    mcts: &mut Mcts<'p, 'a, S, A>,
+    init_state: &S,
+    max_steps: usize,
+) -> (Vec<(S, A, i32)>, bool)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    planned_episode(env, mcts, init_state, max_steps)
+}
@@ -12,6 +12,65 @@ pub enum Policy<'a, S, A> {
     Stochastic(HashMap<&'a S, HashMap<&'a A, f32>>),
 }
 
+impl<'a, S, A> Policy<'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn sample_action(&self, state: &S) -> A {
+        match self {
+            Policy::Deterministic(policy) => policy[state].clone(),
+            Policy::Stochastic(policy) => sample_from_hashmap_dist(&policy[state]).clone(),
+        }
+    }
+
+    pub fn probability(&self, state: &S, action: &A) -> f32 {
+        match self {
+            Policy::Deterministic(policy) => {
+                if policy[state] == *action {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Policy::Stochastic(policy) => policy[state].get(action).copied().unwrap_or(0.0),
+        }
+    }
+}
+
+/// One step of experience, the reward is the one received on reaching `next_state`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Transition<S, A> {
+    pub state: S,
+    pub action: A,
+    pub reward: i32,
+    pub next_state: S,
+}
+
+impl<S, A> Transition<S, A>
+where
+    S: State,
+    A: Action,
+{
+    /// Transitions of a trajectory as produced by `Enviorment::episode`, which leaves out the
+    /// state the episode ended in.
+    pub fn from_trajectory(trajectory: &[(S, A, i32)], final_state: &S) -> Vec<Transition<S, A>> {
+        trajectory
+            .iter()
+            .enumerate()
+            .map(|(i, (state, action, reward))| Transition {
+                state: state.clone(),
+                action: action.clone(),
+                reward: *reward,
+                next_state: trajectory
+                    .get(i + 1)
+                    .map_or(final_state, |step| &step.0)
+                    .clone(),
+            })
+            .collect()
+    }
+}
+
 pub trait State: PartialEq + Eq + Hash + Clone + Debug {}
 
 pub trait Action: PartialEq + Eq + Hash + Clone + Debug {}
@@ -71,4 +130,27 @@ where
             }
         }
     }
+
+    /// Like `episode` but stops after `max_steps` transitions, also reports whether a terminal
+    /// state was reached.
+    fn episode_with_limit(
+        &self,
+        init_state: &S,
+        pol: &Policy<'a, S, A>,
+        max_steps: usize,
+    ) -> (Vec<(S, A, i32)>, bool) {
+        let mut state = init_state.clone();
+        let mut trajectory = Vec::new();
+        while trajectory.len() < max_steps {
+            if self.is_terminal(&state) {
+                return (trajectory, true);
+            }
+            let action = pol.sample_action(&state);
+            let (next_state, reward) = self.response(&state, &action);
+            trajectory.push((state, action, reward));
+            state = next_state;
+        }
+        let terminated = self.is_terminal(&state);
+        (trajectory, terminated)
+    }
 }
@@ -1,3 +1,28 @@
+pub mod average_reward;
+pub mod basis_functions;
+pub mod double_learning;
+pub mod dyna;
+pub mod eligibility_traces;
+pub mod evaluation;
+pub mod function_approximation;
+pub mod gradient_td;
+pub mod least_squares;
+pub mod mcts;
 pub mod mdp;
+pub mod model_learning;
 pub mod monte_carlo_control;
+pub mod monte_carlo_prediction;
+pub mod n_step;
+pub mod neural_network;
+pub mod off_policy_monte_carlo;
+pub mod off_policy_n_step;
+pub mod off_policy_traces;
 pub mod policy_iteration;
+pub mod prioritized_sweeping;
+pub mod random_mdp;
+pub mod rollout_planning;
+pub mod semi_gradient;
+pub mod td_control;
+pub mod temporal_difference;
+pub mod tile_coding;
+pub mod trajectory_sampling;
@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+
+use super::mdp::{Action, EnviormentModel, State, Transition};
+use crate::utils::stats::sample_from_hashmap_dist;
+
+/// Maximum likelihood tabular model built from observed transitions. The dynamics of a pair are
+/// the observed frequencies of each (next state, reward) outcome. With `smoothing` above zero a
+/// symmetric Dirichlet prior of that concentration is added over the outcomes seen from the same
+/// state with any action, and every known action becomes available in every visited state.
+/// States that were reached but never acted in have no estimated dynamics and are treated as
+/// terminal, so planners back up a value of zero from them instead of an empty distribution.
+#[derive(Debug, Clone)]
+pub struct EmpiricalModel<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub smoothing: f32,
+    counts: HashMap<(S, A), HashMap<(S, i32), u32>>,
+    visits: HashMap<(S, A), u32>,
+    state_actions: HashMap<S, Vec<A>>,
+    outcomes: HashMap<S, HashSet<(S, i32)>>,
+    actions: Vec<A>,
+    states: Vec<S>,
+    known: HashSet<S>,
+    terminals: HashSet<S>,
+}
+
+impl<S, A> EmpiricalModel<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn new(smoothing: f32) -> Self {
+        EmpiricalModel {
+            smoothing,
+            counts: HashMap::new(),
+            visits: HashMap::new(),
+            state_actions: HashMap::new(),
+            outcomes: HashMap::new(),
+            actions: Vec::new(),
+            states: Vec::new(),
+            known: HashSet::new(),
+            terminals: HashSet::new(),
+        }
+    }
+
+    pub fn add_transition(&mut self, transition: &Transition<S, A>) {
+        self.add_state(&transition.state);
+        self.add_state(&transition.next_state);
+        if !self.actions.contains(&transition.action) {
+            self.actions.push(transition.action.clone());
+        }
+        let pair = (transition.state.clone(), transition.action.clone());
+        let outcome = (transition.next_state.clone(), transition.reward);
+        let tried = self
+            .state_actions
+            .entry(transition.state.clone())
+            .or_default();
+        if !tried.contains(&transition.action) {
+            tried.push(transition.action.clone());
+        }
+        *self
+            .counts
+            .entry(pair.clone())
+            .or_default()
+            .entry(outcome.clone())
+            .or_insert(0) += 1;
+        *self.visits.entry(pair).or_insert(0) += 1;
+        self.outcomes
+            .entry(transition.state.clone())
+            .or_default()
+            .insert(outcome);
+    }
+
+    /// Adds every step of `trajectory`, `final_state` is where it ended and is remembered as
+    /// terminal when `terminal` is set (episodes cut short should pass false).
+    pub fn add_episode(&mut self, trajectory: &[(S, A, i32)], final_state: &S, terminal: bool) {
+        for transition in Transition::from_trajectory(trajectory, final_state) {
+            self.add_transition(&transition);
+        }
+        self.add_state(final_state);
+        if terminal {
+            self.terminals.insert(final_state.clone());
+        }
+    }
+
+    pub fn mark_terminal(&mut self, state: &S) {
+        self.add_state(state);
+        self.terminals.insert(state.clone());
+    }
+
+    /// Times `action` was taken in `state`.
+    pub fn visits(&self, state: &S, action: &A) -> u32 {
+        self.visits
+            .get(&(state.clone(), action.clone()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Times `action` in `state` led to `next_state` with `reward`.
+    pub fn count(&self, state: &S, action: &A, next_state: &S, reward: i32) -> u32 {
+        self.counts
+            .get(&(state.clone(), action.clone()))
+            .and_then(|outcomes| outcomes.get(&(next_state.clone(), reward)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Number of transitions added so far.
+    pub fn len(&self) -> usize {
+        self.visits.values().map(|&n| n as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visits.is_empty()
+    }
+
+    fn add_state(&mut self, state: &S) {
+        if self.known.insert(state.clone()) {
+            self.states.push(state.clone());
+        }
+    }
+}
+
+impl<S, A> EnviormentModel<S, A> for EmpiricalModel<S, A>
+where
+    S: State,
+    A: Action,
+{
+    fn dynamics(&self, state: &S, action: &A) -> HashMap<(S, i32), f32> {
+        let mut distribution = HashMap::new();
+        if self.is_terminal(state) {
+            return distribution;
+        }
+        let counts = self.counts.get(&(state.clone(), action.clone()));
+        let n = self.visits(state, action) as f32;
+        if self.smoothing > 0.0 {
+            let Some(support) = self.outcomes.get(state) else {
+                return distribution;
+            };
+            let total = n + self.smoothing * support.len() as f32;
+            for outcome in support {
+                let count = counts
+                    .and_then(|counts| counts.get(outcome))
+                    .copied()
+                    .unwrap_or(0);
+                distribution.insert(outcome.clone(), (count as f32 + self.smoothing) / total);
+            }
+        } else if let Some(counts) = counts {
+            for (outcome, count) in counts {
+                distribution.insert(outcome.clone(), *count as f32 / n);
+            }
+        }
+        distribution
+    }
+    /// The actions tried in `state`, or every known action when smoothing.
+    fn posible_actions(&self, state: &S) -> Vec<A> {
+        match self.state_actions.get(state) {
+            Some(tried) if self.smoothing <= 0.0 => tried.clone(),
+            _ => self.actions.clone(),
+        }
+    }
+    fn get_states(&self) -> Vec<S> {
+        self.states.clone()
+    }
+    fn response(&self, state: &S, action: &A) -> (S, i32) {
+        let distribution = self.dynamics(state, action);
+        if distribution.is_empty() {
+            return (state.clone(), 0);
+        }
+        sample_from_hashmap_dist(&distribution)
+    }
+    fn is_terminal(&self, state: &S) -> bool {
+        self.terminals.contains(state) || !self.state_actions.contains_key(state)
+    }
+}
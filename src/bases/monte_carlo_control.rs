@@ -15,7 +15,8 @@ pub fn first_visit_monte_carlo_control<'a, E, S, A>(
     env: E,
     epsilon: f32,
     gamma: f32,
-) where
+) -> Vec<f64>
+where
     S: State,
     A: Action,
     E: Enviorment<'a, S, A>,
@@ -98,6 +99,7 @@ pub fn first_visit_monte_carlo_control<'a, E, S, A>(
     // Save the result
     root_area.present().unwrap();
     println!("Graph saved to graph.png");
+    loss_curve
 }
 
 pub fn update_policy<'a, E, S, A>(
@@ -120,15 +122,7 @@ pub fn update_policy<'a, E, S, A>(
         .clone();
     */
     let actions = env.posible_actions(state);
-    let max_action: A = actions
-        .iter()
-        .max_by(|&x, &y| {
-            action_values[&(state.clone(), x.clone())]
-                .partial_cmp(&action_values[&(state.clone(), y.clone())])
-                .unwrap()
-        })
-        .unwrap()
-        .clone();
+    let max_action = greedy_action(env, action_values, state);
     /*
     if old_max_action != max_action {
         println!("{:?}", state);
@@ -151,4 +145,202 @@ pub fn update_policy<'a, E, S, A>(
     }
 }
 
+/// On-policy every-visit Monte Carlo control with an ε-soft policy. Episodes are cut after
+/// `max_steps` transitions since a policy with ε = 0 may never terminate.
+#[allow(clippy::too_many_arguments)]
+pub fn every_visit_monte_carlo_control<'a, E, S, A>(
+    init_pol: Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    epsilon: f32,
+    gamma: f32,
+    max_steps: usize,
+) -> (HashMap<(S, A), f32>, Policy<'a, S, A>)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut counts: HashMap<(S, A), u32> = HashMap::new();
+    let mut map = match init_pol {
+        Policy::Stochastic(map) => map,
+        Policy::Deterministic(table) => {
+            return every_visit_table_control(
+                table,
+                vals,
+                init_states,
+                episodes,
+                env,
+                epsilon,
+                gamma,
+                max_steps,
+            )
+        }
+    };
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let (trajectory, _) =
+            env.episode_with_limit(init_state, &Policy::Stochastic(map.clone()), max_steps);
+        let mut g = 0.0;
+        for (state, action, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            let pair = (state.clone(), action.clone());
+            let n = counts.entry(pair.clone()).or_insert(0);
+            *n += 1;
+            let val = vals.entry(pair).or_insert(0.0);
+            *val += (g - *val) / *n as f32;
+            update_policy(env, &mut map, &vals, state, epsilon);
+        }
+    }
+    (vals, Policy::Stochastic(map))
+}
+
+/// `every_visit_monte_carlo_control` for a deterministic table, made ε-soft by acting at random
+/// with probability ε and in states missing from it. The table is kept greedy with respect to
+/// the estimates and returned as the policy.
+#[allow(clippy::too_many_arguments)]
+fn every_visit_table_control<'a, E, S, A>(
+    mut table: HashMap<S, A>,
+    mut vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    epsilon: f32,
+    gamma: f32,
+    max_steps: usize,
+) -> (HashMap<(S, A), f32>, Policy<'a, S, A>)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut counts: HashMap<(S, A), u32> = HashMap::new();
+    for _ in 0..episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut trajectory = Vec::new();
+        while !env.is_terminal(&state) && trajectory.len() < max_steps {
+            let action = match table.get(&state) {
+                Some(action) if rng.gen::<f32>() >= epsilon => action.clone(),
+                _ => env
+                    .posible_actions(&state)
+                    .choose(&mut rng)
+                    .unwrap()
+                    .clone(),
+            };
+            let (next_state, reward) = env.response(&state, &action);
+            trajectory.push((state, action, reward));
+            state = next_state;
+        }
+        let mut g = 0.0;
+        for (state, action, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            let pair = (state.clone(), action.clone());
+            let n = counts.entry(pair.clone()).or_insert(0);
+            *n += 1;
+            let val = vals.entry(pair).or_insert(0.0);
+            *val += (g - *val) / *n as f32;
+            table.insert(state.clone(), greedy_action(env, &vals, state));
+        }
+    }
+    (vals, Policy::Deterministic(table))
+}
+
+/// Monte Carlo ES: every episode starts from a random non terminal state-action pair and then
+/// follows the current policy, which is made greedy with respect to the returns seen so far.
+/// Episodes are cut after `max_steps` transitions since a greedy policy may never terminate.
+pub fn monte_carlo_exploring_starts<'a, E, S, A>(
+    init_pol: Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+    max_steps: usize,
+) -> (HashMap<(S, A), f32>, Policy<'a, S, A>)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut counts: HashMap<(S, A), u32> = HashMap::new();
+    let mut pol = init_pol;
+    let states: Vec<S> = env
+        .get_states()
+        .into_iter()
+        .filter(|state| !env.is_terminal(state))
+        .collect();
+    for _ in 0..episodes {
+        let state = states.choose(&mut rng).unwrap().clone();
+        let action = env
+            .posible_actions(&state)
+            .choose(&mut rng)
+            .unwrap()
+            .clone();
+        let (next_state, reward) = env.response(&state, &action);
+        let (rest, _) = env.episode_with_limit(&next_state, &pol, max_steps);
+        let mut trajectory = vec![(state, action, reward)];
+        trajectory.extend(rest);
+
+        let mut first_visits = HashMap::new();
+        for (t, (state, action, _)) in trajectory.iter().enumerate() {
+            first_visits
+                .entry((state.clone(), action.clone()))
+                .or_insert(t);
+        }
+        let mut g = 0.0;
+        for (t, (state, action, reward)) in trajectory.iter().enumerate().rev() {
+            g = gamma * g + *reward as f32;
+            let pair = (state.clone(), action.clone());
+            if first_visits[&pair] != t {
+                continue;
+            }
+            let n = counts.entry(pair.clone()).or_insert(0);
+            *n += 1;
+            let val = vals.entry(pair).or_insert(0.0);
+            *val += (g - *val) / *n as f32;
+            match &mut pol {
+                Policy::Deterministic(map) => {
+                    map.insert(state.clone(), greedy_action(env, &vals, state));
+                }
+                Policy::Stochastic(map) => update_policy(env, map, &vals, state, 0.0),
+            }
+        }
+    }
+    (vals, pol)
+}
+
+/// Greedy action with ties broken at random, pairs missing from `action_values` are worth zero.
+/// Shared by the Monte Carlo and TD control methods.
+pub fn greedy_action<'a, E, S, A>(env: &E, action_values: &HashMap<(S, A), f32>, state: &S) -> A
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let actions = env.posible_actions(state);
+    let values: Vec<f32> = actions
+        .iter()
+        .map(|action| {
+            action_values
+                .get(&(state.clone(), action.clone()))
+                .copied()
+                .unwrap_or(0.0)
+        })
+        .collect();
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let best: Vec<&A> = actions
+        .iter()
+        .zip(&values)
+        .filter(|(_, &v)| v == max)
+        .map(|(action, _)| action)
+        .collect();
+    (*best.choose(&mut thread_rng()).unwrap()).clone()
+}
+
 pub fn rand_init() {}
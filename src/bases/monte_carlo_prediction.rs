@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+
+pub fn first_visit_monte_carlo_prediction<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    monte_carlo_prediction(pol, init_states, episodes, env, gamma, true)
+}
+
+pub fn every_visit_monte_carlo_prediction<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    monte_carlo_prediction(pol, init_states, episodes, env, gamma, false)
+}
+
+fn monte_carlo_prediction<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+    first_visit: bool,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values: HashMap<S, f32> = HashMap::new();
+    let mut counts: HashMap<S, u32> = HashMap::new();
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, pol);
+        let mut first_visits = HashMap::new();
+        for (t, (state, _, _)) in trajectory.iter().enumerate() {
+            first_visits.entry(state.clone()).or_insert(t);
+        }
+        let mut g = 0.0;
+        for (t, (state, _, reward)) in trajectory.iter().enumerate().rev() {
+            g = gamma * g + *reward as f32;
+            if first_visit && first_visits[state] != t {
+                continue;
+            }
+            let n = counts.entry(state.clone()).or_insert(0);
+            *n += 1;
+            let value = values.entry(state.clone()).or_insert(0.0);
+            *value += (g - *value) / *n as f32;
+        }
+    }
+    values
+}
+
+/// Every-visit Monte Carlo with a constant step size instead of sample averages.
+pub fn constant_alpha_monte_carlo_prediction<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_values: HashMap<S, f32>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    alpha: f32,
+    gamma: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values = init_values;
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, pol);
+        let mut g = 0.0;
+        for (state, _, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            let value = values.entry(state.clone()).or_insert(0.0);
+            *value += alpha * (g - *value);
+        }
+    }
+    values
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::td_control::{
+    action_value, epsilon_greedy, expected_action_value, greedy_policy, TdControlResult,
+    TdParameters,
+};
+use super::temporal_difference::rms_error;
+use crate::utils::ring_buffer::RingBuffer;
+
+/// n-step TD prediction (Section 7.1), the last n rewards are kept in a ring buffer of size
+/// n + 1 and each state is updated once its n-step return is available.
+#[allow(clippy::too_many_arguments)]
+pub fn n_step_td_prediction<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_values: HashMap<S, f32>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    n: usize,
+    alpha: f32,
+    gamma: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    assert!(n >= 1, "n-step methods need n of at least one");
+    let mut rng = thread_rng();
+    let mut values = init_values;
+    let mut buffer: RingBuffer<(S, i32)> = RingBuffer::new(n + 1);
+    for _ in 0..episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            continue;
+        }
+        buffer.clear();
+        let mut end = usize::MAX;
+        let mut t = 0;
+        loop {
+            if t < end {
+                let action = pol.sample_action(&state);
+                let (next_state, reward) = env.response(&state, &action);
+                buffer.set(t, (state, reward));
+                state = next_state;
+                if env.is_terminal(&state) {
+                    end = t + 1;
+                }
+            }
+            if t + 1 >= n {
+                let tau = t + 1 - n;
+                let mut g = discounted_rewards(&buffer, tau, (tau + n).min(end), gamma, |x| x.1);
+                if tau + n < end {
+                    g += gamma.powi(n as i32) * values.get(&state).copied().unwrap_or(0.0);
+                }
+                let value = values.entry(buffer.get(tau).0.clone()).or_insert(0.0);
+                *value += alpha * (g - *value);
+                if tau + 1 == end {
+                    break;
+                }
+            }
+            t += 1;
+        }
+    }
+    values
+}
+
+pub fn n_step_sarsa<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    n: usize,
+    params: &TdParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    n_step_control(env, init_vals, init_states, n, params, false)
+}
+
+/// Like n-step Sarsa but the last step of the return bootstraps from the expected value of the
+/// ε-greedy policy.
+pub fn n_step_expected_sarsa<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    n: usize,
+    params: &TdParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    n_step_control(env, init_vals, init_states, n, params, true)
+}
+
+fn n_step_control<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    n: usize,
+    params: &TdParameters,
+    expected: bool,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    assert!(n >= 1, "n-step methods need n of at least one");
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut returns = Vec::new();
+    let mut lengths = Vec::new();
+    let mut buffer: RingBuffer<(S, A, i32)> = RingBuffer::new(n + 1);
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            // Keeps one entry per episode, an episode that starts terminal has no steps.
+            returns.push(0.0);
+            lengths.push(0);
+            continue;
+        }
+        buffer.clear();
+        let mut action = epsilon_greedy(env, &vals, &state, params.epsilon);
+        let mut end = usize::MAX;
+        let mut truncated = false;
+        let mut total = 0.0;
+        let mut t = 0;
+        loop {
+            if t < end {
+                let (next_state, reward) = env.response(&state, &action);
+                total += reward as f32;
+                buffer.set(t, (state, action.clone(), reward));
+                state = next_state;
+                if env.is_terminal(&state) {
+                    end = t + 1;
+                } else {
+                    action = epsilon_greedy(env, &vals, &state, params.epsilon);
+                    if t + 1 >= params.max_steps {
+                        end = t + 1;
+                        truncated = true;
+                    }
+                }
+            }
+            if t + 1 >= n {
+                let tau = t + 1 - n;
+                let horizon = (tau + n).min(end);
+                let mut g = discounted_rewards(&buffer, tau, horizon, params.gamma, |x| x.2);
+                if tau + n < end || truncated {
+                    let next_value = if expected {
+                        expected_action_value(env, &vals, &state, params.epsilon)
+                    } else {
+                        action_value(&vals, &state, &action)
+                    };
+                    g += params.gamma.powi((horizon - tau) as i32) * next_value;
+                }
+                let (s_tau, a_tau, _) = buffer.get(tau);
+                let val = vals.entry((s_tau.clone(), a_tau.clone())).or_insert(0.0);
+                *val += params.alpha * (g - *val);
+                if tau + 1 == end {
+                    break;
+                }
+            }
+            t += 1;
+        }
+        returns.push(total);
+        lengths.push(end);
+    }
+    let policy = greedy_policy(env, &vals);
+    TdControlResult {
+        action_values: vals,
+        policy,
+        returns,
+        lengths,
+    }
+}
+
+/// Sum of the rewards stored for steps `from..to`, discounted from `from`.
+pub fn discounted_rewards<T, F>(
+    buffer: &RingBuffer<T>,
+    from: usize,
+    to: usize,
+    gamma: f32,
+    reward: F,
+) -> f32
+where
+    F: Fn(&T) -> i32,
+{
+    let mut g = 0.0;
+    let mut discount = 1.0;
+    for i in from..to {
+        g += discount * reward(buffer.get(i)) as f32;
+        discount *= gamma;
+    }
+    g
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SweepPoint {
+    pub n: usize,
+    pub alpha: f32,
+    pub rms: f32,
+}
+
+/// Runs n-step TD prediction for every combination of `ns` and `alphas` and reports the RMS
+/// error against `reference` averaged over the first `episodes` episodes and over `runs`
+/// independent runs, as in Figure 7.2. Values start at zero.
+#[allow(clippy::too_many_arguments)]
+pub fn n_step_sweep<'a, E, S, A>(
+    env: &E,
+    pol: &Policy<'a, S, A>,
+    init_states: &[S],
+    reference: &HashMap<S, f32>,
+    ns: &[usize],
+    alphas: &[f32],
+    episodes: u32,
+    runs: u32,
+    gamma: f32,
+) -> Vec<SweepPoint>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let states: Vec<S> = reference.keys().cloned().collect();
+    let mut points = Vec::new();
+    for &n in ns {
+        for &alpha in alphas {
+            let mut total = 0.0;
+            for _ in 0..runs {
+                let mut values = HashMap::new();
+                for _ in 0..episodes {
+                    values =
+                        n_step_td_prediction(pol, values, init_states, 1, env, n, alpha, gamma);
+                    total += rms_error(&values, reference, &states);
+                }
+            }
+            points.push(SweepPoint {
+                n,
+                alpha,
+                rms: total / (episodes * runs) as f32,
+            });
+        }
+    }
+    points
+}
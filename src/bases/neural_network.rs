@@ -0,0 +1,691 @@
+use std::error::Error;
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::function_approximation::{dot, ActionValueFunction, PolicyApproximation, ValueFunction};
+use super::mdp::Action;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activation {
+    Identity,
+    Relu,
+    Tanh,
+    /// Normalizes the whole layer into a distribution.
+    Softmax,
+}
+
+impl Activation {
+    pub fn apply(&self, z: &[f32]) -> Vec<f32> {
+        match self {
+            Activation::Identity => z.to_vec(),
+            Activation::Relu => z.iter().map(|&v| v.max(0.0)).collect(),
+            Activation::Tanh => z.iter().map(|&v| v.tanh()).collect(),
+            Activation::Softmax => softmax(z),
+        }
+    }
+
+    /// Gradient with respect to the inputs of the activation from the one with respect to its
+    /// outputs `y`.
+    fn backward(&self, y: &[f32], gradient: &[f32]) -> Vec<f32> {
+        match self {
+            Activation::Identity => gradient.to_vec(),
+            Activation::Relu => y
+                .iter()
+                .zip(gradient)
+                .map(|(&y, &g)| if y > 0.0 { g } else { 0.0 })
+                .collect(),
+            Activation::Tanh => y
+                .iter()
+                .zip(gradient)
+                .map(|(&y, &g)| g * (1.0 - y * y))
+                .collect(),
+            Activation::Softmax => {
+                let expected = dot(y, gradient);
+                y.iter()
+                    .zip(gradient)
+                    .map(|(&y, &g)| y * (g - expected))
+                    .collect()
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Activation::Identity => "identity",
+            Activation::Relu => "relu",
+            Activation::Tanh => "tanh",
+            Activation::Softmax => "softmax",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "identity" => Some(Activation::Identity),
+            "relu" => Some(Activation::Relu),
+            "tanh" => Some(Activation::Tanh),
+            "softmax" => Some(Activation::Softmax),
+            _ => None,
+        }
+    }
+}
+
+/// e^z normalized to sum one, shifted by the largest entry so it cannot overflow.
+pub fn softmax(z: &[f32]) -> Vec<f32> {
+    let max = z.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = z.iter().map(|&v| (v - max).exp()).collect();
+    let total: f32 = exp.iter().sum();
+    exp.into_iter().map(|e| e / total).collect()
+}
+
+/// Fully connected layer y = f(Wx + b).
+#[derive(Debug, Clone)]
+pub struct Dense {
+    pub inputs: usize,
+    pub outputs: usize,
+    /// W stored row by row, one row per output.
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>,
+    pub activation: Activation,
+}
+
+impl Dense {
+    /// Weights uniform in ±√(6 / (inputs + outputs)), the Glorot initialization, and zero
+    /// biases.
+    pub fn new<R: Rng>(inputs: usize, outputs: usize, activation: Activation, rng: &mut R) -> Self {
+        let limit = (6.0 / (inputs + outputs) as f32).sqrt();
+        Dense {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs)
+                .map(|_| rng.gen_range(-limit..limit))
+                .collect(),
+            biases: vec![0.0; outputs],
+            activation,
+        }
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.weights.len() + self.biases.len()
+    }
+
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let z: Vec<f32> = self
+            .weights
+            .chunks(self.inputs)
+            .zip(&self.biases)
+            .map(|(row, b)| dot(row, input) + b)
+            .collect();
+        self.activation.apply(&z)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptimizerKind {
+    Sgd,
+    /// Steps divided by a running root mean square of the past directions.
+    RmsProp {
+        decay: f32,
+    },
+    /// RMSProp with momentum and bias correction of both averages.
+    Adam {
+        beta1: f32,
+        beta2: f32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Optimizer {
+    pub kind: OptimizerKind,
+    pub learning_rate: f32,
+    /// Directions longer than this are scaled down to it before the step.
+    pub clip_norm: Option<f32>,
+    pub epsilon: f32,
+    first: Vec<f32>,
+    second: Vec<f32>,
+    steps: i32,
+}
+
+impl Optimizer {
+    fn new(kind: OptimizerKind, learning_rate: f32) -> Self {
+        Optimizer {
+            kind,
+            learning_rate,
+            clip_norm: None,
+            epsilon: 1e-8,
+            first: Vec::new(),
+            second: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    pub fn sgd(learning_rate: f32) -> Self {
+        Optimizer::new(OptimizerKind::Sgd, learning_rate)
+    }
+
+    pub fn rms_prop(learning_rate: f32) -> Self {
+        Optimizer::new(OptimizerKind::RmsProp { decay: 0.9 }, learning_rate)
+    }
+
+    pub fn adam(learning_rate: f32) -> Self {
+        Optimizer::new(
+            OptimizerKind::Adam {
+                beta1: 0.9,
+                beta2: 0.999,
+            },
+            learning_rate,
+        )
+    }
+
+    pub fn with_clip_norm(self, clip_norm: f32) -> Self {
+        Optimizer {
+            clip_norm: Some(clip_norm),
+            ..self
+        }
+    }
+
+    /// Moves `parameters` along the ascent `direction`, the crate's updates all being of the
+    /// form w ← w + α d.
+    pub fn step(&mut self, parameters: &mut [f32], direction: &[f32]) {
+        let mut direction = direction.to_vec();
+        if let Some(max_norm) = self.clip_norm {
+            clip_norm(&mut direction, max_norm);
+        }
+        if self.first.len() != parameters.len() {
+            self.first = vec![0.0; parameters.len()];
+            self.second = vec![0.0; parameters.len()];
+            self.steps = 0;
+        }
+        self.steps += 1;
+        let rate = self.learning_rate;
+        match self.kind {
+            OptimizerKind::Sgd => {
+                for (p, d) in parameters.iter_mut().zip(&direction) {
+                    *p += rate * d;
+                }
+            }
+            OptimizerKind::RmsProp { decay } => {
+                for ((p, d), s) in parameters.iter_mut().zip(&direction).zip(&mut self.second) {
+                    *s = decay * *s + (1.0 - decay) * d * d;
+                    *p += rate * d / (s.sqrt() + self.epsilon);
+                }
+            }
+            OptimizerKind::Adam { beta1, beta2 } => {
+                let first_correction = 1.0 - beta1.powi(self.steps);
+                let second_correction = 1.0 - beta2.powi(self.steps);
+                for (((p, d), m), v) in parameters
+                    .iter_mut()
+                    .zip(&direction)
+                    .zip(&mut self.first)
+                    .zip(&mut self.second)
+                {
+                    *m = beta1 * *m + (1.0 - beta1) * d;
+                    *v = beta2 * *v + (1.0 - beta2) * d * d;
+                    let m_hat = *m / first_correction;
+                    let v_hat = *v / second_correction;
+                    *p += rate * m_hat / (v_hat.sqrt() + self.epsilon);
+                }
+            }
+        }
+    }
+}
+
+/// Scales `direction` down so its L2 norm is at most `max_norm`.
+pub fn clip_norm(direction: &mut [f32], max_norm: f32) {
+    let norm = dot(direction, direction).sqrt();
+    if norm > max_norm {
+        let scale = max_norm / norm;
+        direction.iter_mut().for_each(|d| *d *= scale);
+    }
+}
+
+/// Multilayer perceptron trained by backpropagation (Section 9.7), with the optimizer that
+/// applies its updates.
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub layers: Vec<Dense>,
+    pub optimizer: Optimizer,
+}
+
+impl Network {
+    /// Layers of the given sizes, `sizes[0]` being the inputs, with `hidden` on every layer but
+    /// the last, which uses `output`.
+    pub fn new(
+        sizes: &[usize],
+        hidden: Activation,
+        output: Activation,
+        optimizer: Optimizer,
+        seed: Option<u64>,
+    ) -> Self {
+        assert!(
+            sizes.len() >= 2,
+            "a network needs inputs and at least one layer"
+        );
+        assert!(
+            sizes.iter().all(|&n| n > 0),
+            "every layer needs at least one unit"
+        );
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let layers = sizes
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                let activation = if i + 2 == sizes.len() { output } else { hidden };
+                Dense::new(pair[0], pair[1], activation, &mut rng)
+            })
+            .collect();
+        Network { layers, optimizer }
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.layers[0].inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.layers[self.layers.len() - 1].outputs
+    }
+
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        self.layers
+            .iter()
+            .fold(input.to_vec(), |x, layer| layer.forward(&x))
+    }
+
+    /// The input followed by the output of every layer.
+    fn activations(&self, input: &[f32]) -> Vec<Vec<f32>> {
+        let mut activations = vec![input.to_vec()];
+        for layer in &self.layers {
+            let x = layer.forward(&activations[activations.len() - 1]);
+            activations.push(x);
+        }
+        activations
+    }
+
+    /// Gradient of gᵀy with respect to the parameters, y being the outputs for `input` and g
+    /// the `output_gradient`. Flattened like `parameters`.
+    pub fn backward(&self, input: &[f32], output_gradient: &[f32]) -> Vec<f32> {
+        let activations = self.activations(input);
+        let mut gradients: Vec<Vec<f32>> = vec![Vec::new(); self.layers.len()];
+        let mut gradient = output_gradient.to_vec();
+        for (l, layer) in self.layers.iter().enumerate().rev() {
+            let delta = layer.activation.backward(&activations[l + 1], &gradient);
+            let x = &activations[l];
+            let mut layer_gradient = Vec::with_capacity(layer.parameter_count());
+            for d in &delta {
+                layer_gradient.extend(x.iter().map(|x_i| d * x_i));
+            }
+            layer_gradient.extend_from_slice(&delta);
+            gradient = (0..layer.inputs)
+                .map(|i| {
+                    delta
+                        .iter()
+                        .enumerate()
+                        .map(|(o, d)| layer.weights[o * layer.inputs + i] * d)
+                        .sum()
+                })
+                .collect();
+            gradients[l] = layer_gradient;
+        }
+        gradients.concat()
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.layers.iter().map(|l| l.parameter_count()).sum()
+    }
+
+    /// Weights then biases of every layer in order.
+    pub fn parameters(&self) -> Vec<f32> {
+        self.layers
+            .iter()
+            .flat_map(|l| l.weights.iter().chain(&l.biases).copied())
+            .collect()
+    }
+
+    pub fn set_parameters(&mut self, parameters: &[f32]) {
+        let mut rest = parameters;
+        for layer in self.layers.iter_mut() {
+            let (weights, tail) = rest.split_at(layer.weights.len());
+            let (biases, tail) = tail.split_at(layer.biases.len());
+            layer.weights.copy_from_slice(weights);
+            layer.biases.copy_from_slice(biases);
+            rest = tail;
+        }
+    }
+
+    /// Moves the parameters along the ascent `direction` with the optimizer.
+    pub fn apply(&mut self, direction: &[f32]) {
+        let mut parameters = self.parameters();
+        self.optimizer.step(&mut parameters, direction);
+        self.set_parameters(&parameters);
+    }
+
+    /// One line per layer with its inputs, outputs, activation and parameters, which
+    /// `from_text` reads back exactly.
+    pub fn to_text(&self) -> String {
+        self.layers
+            .iter()
+            .map(|l| {
+                let parameters: Vec<String> = l
+                    .weights
+                    .iter()
+                    .chain(&l.biases)
+                    .map(|p| p.to_string())
+                    .collect();
+                format!(
+                    "{} {} {} {}\n",
+                    l.inputs,
+                    l.outputs,
+                    l.activation.name(),
+                    parameters.join(" ")
+                )
+            })
+            .collect()
+    }
+
+    pub fn from_text(text: &str, optimizer: Optimizer) -> Result<Self, Box<dyn Error>> {
+        let mut layers = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let mut next = || fields.next().ok_or("truncated layer");
+            let inputs: usize = next()?.parse()?;
+            let outputs: usize = next()?.parse()?;
+            let name = next()?;
+            let activation = Activation::parse(name).ok_or(format!("unknown activation {name}"))?;
+            let parameters = fields.map(|f| f.parse()).collect::<Result<Vec<f32>, _>>()?;
+            if inputs == 0 || outputs == 0 {
+                return Err(format!("layer {} has no units", layers.len()).into());
+            }
+            if let Some(previous) = layers.last().map(|l: &Dense| l.outputs) {
+                if inputs != previous {
+                    return Err(format!(
+                        "layer {} takes {inputs} inputs but the one before has {previous} outputs",
+                        layers.len()
+                    )
+                    .into());
+                }
+            }
+            if parameters.len() != (inputs + 1) * outputs {
+                return Err(format!("expected {} parameters", (inputs + 1) * outputs).into());
+            }
+            let (weights, biases) = parameters.split_at(inputs * outputs);
+            layers.push(Dense {
+                inputs,
+                outputs,
+                weights: weights.to_vec(),
+                biases: biases.to_vec(),
+                activation,
+            });
+        }
+        if layers.is_empty() {
+            return Err("no layers".into());
+        }
+        Ok(Network { layers, optimizer })
+    }
+
+    pub fn save(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(file_path, self.to_text())?;
+        Ok(())
+    }
+
+    pub fn load(file_path: &str, optimizer: Optimizer) -> Result<Self, Box<dyn Error>> {
+        Network::from_text(&fs::read_to_string(file_path)?, optimizer)
+    }
+}
+
+/// A network with a single output as v̂(s, w). With `Optimizer::sgd(1.0)` the update is exactly
+/// w ← w + step ∇v̂, the adaptive optimizers rescale it.
+impl ValueFunction for Network {
+    fn value(&self, features: &[f32]) -> f32 {
+        self.forward(features)[0]
+    }
+    fn gradient(&self, features: &[f32]) -> Vec<f32> {
+        self.backward(features, &[1.0])
+    }
+    fn update(&mut self, features: &[f32], step: f32) {
+        let direction: Vec<f32> = self
+            .gradient(features)
+            .into_iter()
+            .map(|g| step * g)
+            .collect();
+        self.apply(&direction);
+    }
+}
+
+fn one_hot(size: usize, index: usize) -> Vec<f32> {
+    let mut x = vec![0.0; size];
+    x[index] = 1.0;
+    x
+}
+
+/// Action values with one output per action, the architecture of DQN, so all of them come out
+/// of a single forward pass.
+#[derive(Debug, Clone)]
+pub struct NetworkActionValues<A>
+where
+    A: Action,
+{
+    pub network: Network,
+    /// The action of every output.
+    pub actions: Vec<A>,
+}
+
+impl<A> NetworkActionValues<A>
+where
+    A: Action,
+{
+    pub fn new(network: Network, actions: Vec<A>) -> Self {
+        assert_eq!(network.outputs(), actions.len());
+        NetworkActionValues { network, actions }
+    }
+
+    fn index(&self, action: &A) -> usize {
+        self.actions
+            .iter()
+            .position(|a| a == action)
+            .expect("action without an output")
+    }
+}
+
+impl<A> ActionValueFunction<A> for NetworkActionValues<A>
+where
+    A: Action,
+{
+    fn value(&self, features: &[f32], action: &A) -> f32 {
+        self.network.forward(features)[self.index(action)]
+    }
+    fn gradient(&self, features: &[f32], action: &A) -> Vec<f32> {
+        let output = one_hot(self.actions.len(), self.index(action));
+        self.network.backward(features, &output)
+    }
+    fn update(&mut self, features: &[f32], action: &A, step: f32) {
+        let direction: Vec<f32> = self
+            .gradient(features, action)
+            .into_iter()
+            .map(|g| step * g)
+            .collect();
+        self.network.apply(&direction);
+    }
+}
+
+/// Softmax in action preferences (Eq. 13.2) with the preferences computed by the network, one
+/// output per action and an identity output layer. Only the available actions are normalized.
+#[derive(Debug, Clone)]
+pub struct NetworkPolicy<A>
+where
+    A: Action,
+{
+    pub network: Network,
+    /// The action of every output.
+    pub actions: Vec<A>,
+}
+
+impl<A> NetworkPolicy<A>
+where
+    A: Action,
+{
+    pub fn new(network: Network, actions: Vec<A>) -> Self {
+        assert_eq!(network.outputs(), actions.len());
+        assert_eq!(
+            network.layers[network.layers.len() - 1].activation,
+            Activation::Identity,
+            "the preferences are normalized by the policy, the output layer must be linear"
+        );
+        NetworkPolicy { network, actions }
+    }
+
+    fn indices(&self, actions: &[A]) -> Vec<usize> {
+        actions
+            .iter()
+            .map(|action| {
+                self.actions
+                    .iter()
+                    .position(|a| a == action)
+                    .expect("action without an output")
+            })
+            .collect()
+    }
+}
+
+impl<A> PolicyApproximation<A> for NetworkPolicy<A>
+where
+    A: Action,
+{
+    fn probabilities(&self, features: &[f32], actions: &[A]) -> Vec<f32> {
+        let preferences = self.network.forward(features);
+        let available: Vec<f32> = self
+            .indices(actions)
+            .into_iter()
+            .map(|i| preferences[i])
+            .collect();
+        softmax(&available)
+    }
+    /// ∂ ln π(a)/∂h(b) = 1{a = b} − π(b) for the preferences h, backpropagated.
+    fn log_gradient(&self, features: &[f32], actions: &[A], action: &A) -> Vec<f32> {
+        let probabilities = self.probabilities(features, actions);
+        let mut output = vec![0.0; self.actions.len()];
+        for ((i, p), a) in self
+            .indices(actions)
+            .into_iter()
+            .zip(probabilities)
+            .zip(actions)
+        {
+            output[i] = if a == action { 1.0 - p } else { -p };
+        }
+        self.network.backward(features, &output)
+    }
+    fn update(&mut self, features: &[f32], actions: &[A], action: &A, step: f32) {
+        let direction: Vec<f32> = self
+            .log_gradient(features, actions, action)
+            .into_iter()
+            .map(|g| step * g)
+            .collect();
+        self.network.apply(&direction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bases::random_mdp::MdpAction;
+
+    const INPUT: [f32; 3] = [0.3, -0.7, 0.5];
+    const OUTPUT_GRADIENT: [f32; 2] = [0.8, -0.4];
+
+    /// Largest difference between `gradient` and central differences of `f` in the parameters.
+    fn finite_difference_error<F>(network: &Network, gradient: &[f32], f: F) -> f32
+    where
+        F: Fn(&Network) -> f32,
+    {
+        let h = 1e-2;
+        let parameters = network.parameters();
+        let mut perturbed = network.clone();
+        let mut error: f32 = 0.0;
+        for (i, g) in gradient.iter().enumerate() {
+            let mut shifted = parameters.clone();
+            shifted[i] = parameters[i] + h;
+            perturbed.set_parameters(&shifted);
+            let up = f(&perturbed);
+            shifted[i] = parameters[i] - h;
+            perturbed.set_parameters(&shifted);
+            let down = f(&perturbed);
+            error = error.max(((up - down) / (2.0 * h) - g).abs());
+        }
+        error
+    }
+
+    #[test]
+    fn backward_matches_finite_differences() {
+        for activation in [
+            Activation::Identity,
+            Activation::Relu,
+            Activation::Tanh,
+            Activation::Softmax,
+        ] {
+            let network = Network::new(
+                &[3, 4, 2],
+                activation,
+                activation,
+                Optimizer::sgd(0.1),
+                Some(1),
+            );
+            let gradient = network.backward(&INPUT, &OUTPUT_GRADIENT);
+            assert_eq!(gradient.len(), network.parameter_count());
+            let error = finite_difference_error(&network, &gradient, |n| {
+                dot(&n.forward(&INPUT), &OUTPUT_GRADIENT)
+            });
+            assert!(error < 1e-3, "{activation:?} gradient off by {error}");
+        }
+    }
+
+    #[test]
+    fn policy_log_gradient_matches_finite_differences() {
+        let network = Network::new(
+            &[3, 5, 3],
+            Activation::Tanh,
+            Activation::Identity,
+            Optimizer::sgd(0.1),
+            Some(2),
+        );
+        let policy = NetworkPolicy::new(network, vec![MdpAction(0), MdpAction(1), MdpAction(2)]);
+        let available = [MdpAction(0), MdpAction(2)];
+        let gradient = policy.log_gradient(&INPUT, &available, &MdpAction(2));
+        let error = finite_difference_error(&policy.network, &gradient, |n| {
+            let policy =
+                NetworkPolicy::new(n.clone(), vec![MdpAction(0), MdpAction(1), MdpAction(2)]);
+            policy.probabilities(&INPUT, &available)[1].ln()
+        });
+        assert!(error < 1e-3, "log gradient off by {error}");
+    }
+
+    #[test]
+    fn text_round_trip_is_exact() {
+        let network = Network::new(
+            &[3, 4, 2],
+            Activation::Relu,
+            Activation::Softmax,
+            Optimizer::sgd(0.1),
+            Some(3),
+        );
+        let loaded = Network::from_text(&network.to_text(), Optimizer::sgd(0.1)).unwrap();
+        assert_eq!(loaded.parameters(), network.parameters());
+        for (a, b) in loaded.layers.iter().zip(&network.layers) {
+            assert_eq!(
+                (a.inputs, a.outputs, a.activation),
+                (b.inputs, b.outputs, b.activation)
+            );
+        }
+        assert_eq!(loaded.forward(&INPUT), network.forward(&INPUT));
+    }
+
+    #[test]
+    fn from_text_rejects_mismatched_layers() {
+        let text = "2 1 identity 0 0 0\n2 1 identity 0 0 0\n";
+        assert!(Network::from_text(text, Optimizer::sgd(0.1)).is_err());
+        let text = "0 1 identity 0\n";
+        assert!(Network::from_text(text, Optimizer::sgd(0.1)).is_err());
+    }
+}
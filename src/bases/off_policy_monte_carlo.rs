@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::monte_carlo_control::greedy_action;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportanceSampling {
+    Ordinary,
+    Weighted,
+}
+
+/// Incremental off-policy estimate of the target policy's action values from episodes
+/// generated by the behavior policy (Section 5.6).
+pub fn off_policy_monte_carlo_prediction<'a, E, S, A>(
+    target: &Policy<'a, S, A>,
+    behavior: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+    sampling: ImportanceSampling,
+) -> HashMap<(S, A), f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals: HashMap<(S, A), f32> = HashMap::new();
+    let mut weights: HashMap<(S, A), f32> = HashMap::new();
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, behavior);
+        let mut g = 0.0;
+        let mut w = 1.0;
+        for (state, action, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            let pair = (state.clone(), action.clone());
+            update_estimate(&mut vals, &mut weights, &pair, w, g, sampling);
+            w *= target.probability(state, action) / behavior.probability(state, action);
+            if w == 0.0 && sampling == ImportanceSampling::Weighted {
+                break;
+            }
+        }
+    }
+    vals
+}
+
+/// Every-visit off-policy estimate of the target policy's state values.
+pub fn off_policy_state_value_prediction<'a, E, S, A>(
+    target: &Policy<'a, S, A>,
+    behavior: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+    sampling: ImportanceSampling,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values: HashMap<S, f32> = HashMap::new();
+    let mut weights: HashMap<S, f32> = HashMap::new();
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, behavior);
+        let mut g = 0.0;
+        let mut w = 1.0;
+        for (state, action, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            w *= target.probability(state, action) / behavior.probability(state, action);
+            update_estimate(&mut values, &mut weights, state, w, g, sampling);
+        }
+    }
+    values
+}
+
+#[derive(Debug, Clone)]
+pub struct OffPolicyControlResult<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub action_values: HashMap<(S, A), f32>,
+    /// Greedy target policy over the states visited.
+    pub policy: HashMap<S, A>,
+    /// Length of every episode generated by the behavior policy.
+    pub lengths: Vec<usize>,
+}
+
+/// Off-policy control with a greedy target policy, episodes are generated by the soft behavior
+/// policy and only their tails that agree with the greedy policy are used (Section 5.7).
+pub fn off_policy_monte_carlo_control<'a, E, S, A>(
+    behavior: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+) -> OffPolicyControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut weights: HashMap<(S, A), f32> = HashMap::new();
+    let mut pol: HashMap<S, A> = HashMap::new();
+    let mut lengths = Vec::with_capacity(episodes as usize);
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, behavior);
+        let mut g = 0.0;
+        let mut w = 1.0;
+        for (state, action, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            let pair = (state.clone(), action.clone());
+            let c = weights.entry(pair.clone()).or_insert(0.0);
+            *c += w;
+            let val = vals.entry(pair).or_insert(0.0);
+            *val += w / *c * (g - *val);
+            let best = greedy_action(env, &vals, state);
+            let agrees = best == *action;
+            pol.insert(state.clone(), best);
+            if !agrees {
+                break;
+            }
+            w /= behavior.probability(state, action);
+        }
+        lengths.push(trajectory.len());
+    }
+    OffPolicyControlResult {
+        action_values: vals,
+        policy: pol,
+        lengths,
+    }
+}
+
+/// Discounting-aware importance sampling (Section 5.8), the return is split into flat partial
+/// returns so each one is only corrected by the ratios of the actions that produced it.
+pub fn discounting_aware_prediction<'a, E, S, A>(
+    target: &Policy<'a, S, A>,
+    behavior: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+    sampling: ImportanceSampling,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values: HashMap<S, f32> = HashMap::new();
+    let mut weights: HashMap<S, f32> = HashMap::new();
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, behavior);
+        let ratios: Vec<f32> = trajectory
+            .iter()
+            .map(|(state, action, _)| {
+                target.probability(state, action) / behavior.probability(state, action)
+            })
+            .collect();
+        let end = trajectory.len();
+        for (t, (state, _, _)) in trajectory.iter().enumerate() {
+            let mut flat_return = 0.0;
+            let mut rho = 1.0;
+            let mut discount = 1.0;
+            let mut numerator = 0.0;
+            let mut denominator = 0.0;
+            for h in (t + 1)..=end {
+                flat_return += trajectory[h - 1].2 as f32;
+                rho *= ratios[h - 1];
+                let horizon_weight = if h == end {
+                    discount
+                } else {
+                    (1.0 - gamma) * discount
+                };
+                numerator += horizon_weight * rho * flat_return;
+                denominator += horizon_weight * rho;
+                discount *= gamma;
+            }
+            let n = weights.entry(state.clone()).or_insert(0.0);
+            let value = values.entry(state.clone()).or_insert(0.0);
+            match sampling {
+                ImportanceSampling::Ordinary => {
+                    *n += 1.0;
+                    *value += (numerator - *value) / *n;
+                }
+                ImportanceSampling::Weighted => {
+                    *n += denominator;
+                    if *n > 0.0 {
+                        *value += (numerator - denominator * *value) / *n;
+                    }
+                }
+            }
+        }
+    }
+    values
+}
+
+/// Per-decision importance sampling (Section 5.9), each reward is only corrected by the ratios
+/// of the actions that led to it.
+pub fn per_decision_prediction<'a, E, S, A>(
+    target: &Policy<'a, S, A>,
+    behavior: &Policy<'a, S, A>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    gamma: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values: HashMap<S, f32> = HashMap::new();
+    let mut counts: HashMap<S, f32> = HashMap::new();
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, behavior);
+        let mut g = 0.0;
+        for (state, action, reward) in trajectory.iter().rev() {
+            let rho = target.probability(state, action) / behavior.probability(state, action);
+            g = rho * (*reward as f32 + gamma * g);
+            update_estimate(
+                &mut values,
+                &mut counts,
+                state,
+                1.0,
+                g,
+                ImportanceSampling::Ordinary,
+            );
+        }
+    }
+    values
+}
+
+fn update_estimate<K>(
+    values: &mut HashMap<K, f32>,
+    weights: &mut HashMap<K, f32>,
+    key: &K,
+    w: f32,
+    g: f32,
+    sampling: ImportanceSampling,
+) where
+    K: Eq + Hash + Clone,
+{
+    let c = weights.entry(key.clone()).or_insert(0.0);
+    let value = values.entry(key.clone()).or_insert(0.0);
+    match sampling {
+        ImportanceSampling::Ordinary => {
+            *c += 1.0;
+            *value += (w * g - *value) / *c;
+        }
+        ImportanceSampling::Weighted => {
+            *c += w;
+            if *c > 0.0 {
+                *value += w / *c * (g - *value);
+            }
+        }
+    }
+}
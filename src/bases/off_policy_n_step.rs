@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::n_step::discounted_rewards;
+use super::td_control::{
+    action_value, epsilon_greedy_probabilities, greedy_policy, TdControlResult, TdParameters,
+};
+use crate::utils::ring_buffer::RingBuffer;
+
+/// Off-policy n-step Sarsa (Section 7.3), episodes follow `behavior` and every update is
+/// weighted by the importance ratio of the actions after the one being updated. The target
+/// policy is ε-greedy with respect to the learned values using `params.epsilon`, zero gives the
+/// greedy target.
+pub fn off_policy_n_step_sarsa<'a, E, S, A>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    n: usize,
+    params: &TdParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    assert!(n >= 1, "n-step methods need n of at least one");
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut returns = Vec::new();
+    let mut lengths = Vec::new();
+    let mut steps: RingBuffer<(S, A)> = RingBuffer::new(n + 1);
+    let mut rewards: RingBuffer<i32> = RingBuffer::new(n + 1);
+    for _ in 0..params.episodes {
+        let state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            // Keeps one entry per episode, an episode that starts terminal has no steps.
+            returns.push(0.0);
+            lengths.push(0);
+            continue;
+        }
+        steps.clear();
+        rewards.clear();
+        let action = behavior.sample_action(&state);
+        steps.set(0, (state, action));
+        let mut end = usize::MAX;
+        let mut truncated = false;
+        let mut total = 0.0;
+        let mut t = 0;
+        loop {
+            if t < end {
+                (end, truncated) = advance(env, behavior, &mut steps, &mut rewards, t, params);
+                total += *rewards.get(t + 1) as f32;
+            }
+            if t + 1 >= n {
+                let tau = t + 1 - n;
+                let horizon = (tau + n).min(end);
+                let bootstrap = tau + n < end || truncated;
+                let last = if bootstrap { horizon } else { horizon - 1 };
+                let mut rho = 1.0;
+                for k in (tau + 1)..=last {
+                    let (s_k, a_k) = steps.get(k);
+                    rho *= target_probability(env, &vals, s_k, a_k, params.epsilon)
+                        / behavior.probability(s_k, a_k);
+                }
+                let mut g =
+                    discounted_rewards(&rewards, tau + 1, horizon + 1, params.gamma, |r| *r);
+                if bootstrap {
+                    let (s_h, a_h) = steps.get(horizon);
+                    g += params.gamma.powi((horizon - tau) as i32) * action_value(&vals, s_h, a_h);
+                }
+                let (s_tau, a_tau) = steps.get(tau);
+                let val = vals.entry((s_tau.clone(), a_tau.clone())).or_insert(0.0);
+                *val += params.alpha * rho * (g - *val);
+                if tau + 1 == end {
+                    break;
+                }
+            }
+            t += 1;
+        }
+        returns.push(total);
+        lengths.push(end);
+    }
+    let policy = greedy_policy(env, &vals);
+    TdControlResult {
+        action_values: vals,
+        policy,
+        returns,
+        lengths,
+    }
+}
+
+/// n-step Tree Backup (Section 7.5), the return backs up the expected value of the target
+/// policy over the actions not taken so no importance sampling is needed.
+pub fn tree_backup<'a, E, S, A>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    n: usize,
+    params: &TdParameters,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    q_sigma(env, behavior, init_vals, init_states, n, params, |_| 0.0)
+}
+
+/// n-step Q(σ) (Section 7.6), `sigma(t)` picks for step t between full sampling with importance
+/// ratios (1, n-step Sarsa) and pure expectation (0, Tree Backup), or anything in between.
+pub fn q_sigma<'a, E, S, A, F>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    n: usize,
+    params: &TdParameters,
+    sigma: F,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: Fn(usize) -> f32,
+{
+    assert!(n >= 1, "n-step methods need n of at least one");
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut returns = Vec::new();
+    let mut lengths = Vec::new();
+    let mut steps: RingBuffer<(S, A)> = RingBuffer::new(n + 1);
+    let mut rewards: RingBuffer<i32> = RingBuffer::new(n + 1);
+    for _ in 0..params.episodes {
+        let state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            // Keeps one entry per episode, an episode that starts terminal has no steps.
+            returns.push(0.0);
+            lengths.push(0);
+            continue;
+        }
+        steps.clear();
+        rewards.clear();
+        let action = behavior.sample_action(&state);
+        steps.set(0, (state, action));
+        let mut end = usize::MAX;
+        let mut truncated = false;
+        let mut total = 0.0;
+        let mut t = 0;
+        loop {
+            if t < end {
+                (end, truncated) = advance(env, behavior, &mut steps, &mut rewards, t, params);
+                total += *rewards.get(t + 1) as f32;
+            }
+            if t + 1 >= n {
+                let tau = t + 1 - n;
+                let top = (t + 1).min(end);
+                let mut g = if top < end || truncated {
+                    let (s_top, a_top) = steps.get(top);
+                    action_value(&vals, s_top, a_top)
+                } else {
+                    0.0
+                };
+                for k in ((tau + 1)..=top).rev() {
+                    let reward = *rewards.get(k) as f32;
+                    if k == end && !truncated {
+                        g = reward;
+                        continue;
+                    }
+                    let (s_k, a_k) = steps.get(k);
+                    let probs = epsilon_greedy_probabilities(env, &vals, s_k, params.epsilon);
+                    let expected: f32 = probs
+                        .iter()
+                        .map(|(a, p)| p * action_value(&vals, s_k, a))
+                        .sum();
+                    let pi = probs
+                        .iter()
+                        .find(|(a, _)| a == a_k)
+                        .map(|(_, p)| *p)
+                        .unwrap_or(0.0);
+                    let rho = pi / behavior.probability(s_k, a_k);
+                    let s = sigma(k);
+                    let q = action_value(&vals, s_k, a_k);
+                    g = reward
+                        + params.gamma * (s * rho + (1.0 - s) * pi) * (g - q)
+                        + params.gamma * expected;
+                }
+                let (s_tau, a_tau) = steps.get(tau);
+                let val = vals.entry((s_tau.clone(), a_tau.clone())).or_insert(0.0);
+                *val += params.alpha * (g - *val);
+                if tau + 1 == end {
+                    break;
+                }
+            }
+            t += 1;
+        }
+        returns.push(total);
+        lengths.push(end);
+    }
+    let policy = greedy_policy(env, &vals);
+    TdControlResult {
+        action_values: vals,
+        policy,
+        returns,
+        lengths,
+    }
+}
+
+/// Takes the action stored for step t, stores the reward as step t + 1 and, unless the episode
+/// ended, the next state with the behavior action for it. Returns the episode end (or
+/// `usize::MAX`) and whether the episode was cut by `params.max_steps`.
+fn advance<'a, E, S, A>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    steps: &mut RingBuffer<(S, A)>,
+    rewards: &mut RingBuffer<i32>,
+    t: usize,
+    params: &TdParameters,
+) -> (usize, bool)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let (state, action) = steps.get(t);
+    let (next_state, reward) = env.response(state, action);
+    rewards.set(t + 1, reward);
+    if env.is_terminal(&next_state) {
+        return (t + 1, false);
+    }
+    let next_action = behavior.sample_action(&next_state);
+    steps.set(t + 1, (next_state, next_action));
+    if t + 1 >= params.max_steps {
+        return (t + 1, true);
+    }
+    (usize::MAX, false)
+}
+
+fn target_probability<'a, E, S, A>(
+    env: &E,
+    action_values: &HashMap<(S, A), f32>,
+    state: &S,
+    action: &A,
+    epsilon: f32,
+) -> f32
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    epsilon_greedy_probabilities(env, action_values, state, epsilon)
+        .into_iter()
+        .find(|(a, _)| a == action)
+        .map(|(_, p)| p)
+        .unwrap_or(0.0)
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::eligibility_traces::{apply_traces, update_traces, TraceParameters};
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::td_control::action_value;
+
+/// How the trace of the pairs visited so far is scaled when the behavior takes an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceCorrection {
+    /// Traces decay by γλ and the λ-return follows the behavior actions with no correction, as
+    /// in Peng's Q(λ). The estimates drift from q_π towards the values of the behavior as λ
+    /// grows.
+    Naive,
+    /// Decay by γλρ, unbiased but the product of ratios can blow up.
+    ImportanceSampling,
+    /// Decay by γλπ(a|s) (Section 12.9).
+    TreeBackup,
+    /// Decay by γλ min(1, ρ), the truncated ratios of Retrace(λ).
+    Retrace,
+}
+
+/// Tree-Backup(λ) evaluation of the action values of `target` from episodes of `behavior`.
+pub fn tree_backup_lambda<'a, E, S, A>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    target: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+) -> HashMap<(S, A), f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    off_policy_lambda(
+        env,
+        behavior,
+        target,
+        init_vals,
+        init_states,
+        params,
+        TraceCorrection::TreeBackup,
+    )
+}
+
+/// Retrace(λ) evaluation of the action values of `target`, the importance ratios are truncated at
+/// one so the traces never grow, yet they are not cut as hard as in Tree-Backup when the
+/// policies agree.
+pub fn retrace_lambda<'a, E, S, A>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    target: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+) -> HashMap<(S, A), f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    off_policy_lambda(
+        env,
+        behavior,
+        target,
+        init_vals,
+        init_states,
+        params,
+        TraceCorrection::Retrace,
+    )
+}
+
+/// Backward view shared by the tabular off-policy λ methods. Every step computes the expected
+/// TD error under `target`, δ = R + γ Σ π(a|S') Q(S', a) - Q(S, A), and spreads it over the
+/// traces, which decay according to `correction`. The naive correction mixes the value of the
+/// next behavior action into δ instead. `params.epsilon` is not used.
+pub fn off_policy_lambda<'a, E, S, A>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    target: &Policy<'a, S, A>,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TraceParameters,
+    correction: TraceCorrection,
+) -> HashMap<(S, A), f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            continue;
+        }
+        let mut action = behavior.sample_action(&state);
+        let mut traces: HashMap<(S, A), f32> = HashMap::new();
+        let mut steps = 0;
+        loop {
+            let (next_state, reward) = env.response(&state, &action);
+            steps += 1;
+            let pi = target.probability(&state, &action);
+            let rho = pi / behavior.probability(&state, &action);
+            let coefficient = match correction {
+                TraceCorrection::Naive => 1.0,
+                TraceCorrection::ImportanceSampling => rho,
+                TraceCorrection::TreeBackup => pi,
+                TraceCorrection::Retrace => rho.min(1.0),
+            };
+            let decay = params.gamma * params.lambda * coefficient;
+            let terminal = env.is_terminal(&next_state);
+            let next_action = if terminal {
+                None
+            } else {
+                Some(behavior.sample_action(&next_state))
+            };
+            let mut next_value = expected_target_value(env, target, &vals, &next_state);
+            if let (TraceCorrection::Naive, Some(a)) = (correction, &next_action) {
+                next_value = (1.0 - params.lambda) * next_value
+                    + params.lambda * action_value(&vals, &next_state, a);
+            }
+            let delta =
+                reward as f32 + params.gamma * next_value - action_value(&vals, &state, &action);
+            update_traces(
+                &mut traces,
+                &(state, action),
+                decay,
+                params.trace,
+                params.alpha,
+            );
+            apply_traces(&mut vals, &traces, params.alpha * delta);
+            match next_action {
+                Some(a) if steps < params.max_steps => {
+                    state = next_state;
+                    action = a;
+                }
+                _ => break,
+            }
+        }
+    }
+    vals
+}
+
+/// GQ(λ) (Section 12.11) for linear action values q(s, a) = wᵀx(s, a), with `features` giving
+/// x(s, a) of length `dims`. The secondary weights learn with step size `beta` and correct the
+/// gradient so the method stays stable off-policy. Returns w.
+#[allow(clippy::too_many_arguments)]
+pub fn gq_lambda<'a, E, S, A, F>(
+    env: &E,
+    behavior: &Policy<'a, S, A>,
+    target: &Policy<'a, S, A>,
+    features: F,
+    dims: usize,
+    init_states: &[S],
+    params: &TraceParameters,
+    beta: f32,
+) -> Vec<f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: Fn(&S, &A) -> Vec<f32>,
+{
+    let mut rng = thread_rng();
+    let mut weights = vec![0.0; dims];
+    let mut secondary = vec![0.0; dims];
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut traces = vec![0.0; dims];
+        let mut steps = 0;
+        while !env.is_terminal(&state) && steps < params.max_steps {
+            let action = behavior.sample_action(&state);
+            let (next_state, reward) = env.response(&state, &action);
+            steps += 1;
+            let x = features(&state, &action);
+            let rho = target.probability(&state, &action) / behavior.probability(&state, &action);
+            let x_bar = if env.is_terminal(&next_state) {
+                vec![0.0; dims]
+            } else {
+                let mut x_bar = vec![0.0; dims];
+                for a in env.posible_actions(&next_state) {
+                    let p = target.probability(&next_state, &a);
+                    if p > 0.0 {
+                        for (x_i, f) in x_bar.iter_mut().zip(features(&next_state, &a)) {
+                            *x_i += p * f;
+                        }
+                    }
+                }
+                x_bar
+            };
+            let delta = reward as f32 + params.gamma * dot(&weights, &x_bar) - dot(&weights, &x);
+            for (z, x_i) in traces.iter_mut().zip(&x) {
+                *z = params.gamma * params.lambda * rho * *z + x_i;
+            }
+            let correction = params.gamma * (1.0 - params.lambda) * dot(&traces, &secondary);
+            let prediction = dot(&secondary, &x);
+            for i in 0..dims {
+                weights[i] += params.alpha * (delta * traces[i] - correction * x_bar[i]);
+                secondary[i] += beta * (delta * traces[i] - prediction * x[i]);
+            }
+            state = next_state;
+        }
+    }
+    weights
+}
+
+/// Σ π(a|s) Q(s, a), zero for terminal states.
+fn expected_target_value<'a, E, S, A>(
+    env: &E,
+    target: &Policy<'a, S, A>,
+    action_values: &HashMap<(S, A), f32>,
+    state: &S,
+) -> f32
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    if env.is_terminal(state) {
+        return 0.0;
+    }
+    env.posible_actions(state)
+        .iter()
+        .map(|a| target.probability(state, a) * action_value(action_values, state, a))
+        .sum()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
@@ -52,7 +52,6 @@ where
             break;
         }
     }
-    println!("end pol eval");
     values
 }
 
@@ -100,7 +99,6 @@ where
         }
     }
     if policy_stable {
-        println!("pol improv returns");
         return values;
     }
     values = policy_evaluation(agent, enviorment, states, values, gamma, tolerance);
@@ -164,7 +162,7 @@ where
     });
     loop {
         let mut delta: f32 = 0.0;
-        for (i, state) in states.iter().enumerate() {
+        for state in states.iter() {
             let v = values[state];
             let actions = enviorment.posible_actions(&state);
             let mut max_action = &actions[0];
@@ -191,10 +189,6 @@ where
             delta = delta.max((v - value).abs());
             let val = values.get_mut(state).unwrap();
             *val = value;
-            println!("{i:?}");
-            println!("{delta:?}");
-
-            //println!("{values:?}")
         }
         if delta < tolerance {
             break;
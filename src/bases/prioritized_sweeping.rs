@@ -0,0 +1,415 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::dyna::{DynaParameters, DynaResult, TabularModel};
+use super::mdp::{Action, Enviorment, EnviormentModel, State};
+use super::td_control::{action_value, epsilon_greedy, greedy_policy, max_action_value};
+
+/// Queue entry ordered by priority only, the key indexes the thing to update.
+#[derive(Debug, Clone, Copy)]
+struct Prioritized {
+    priority: f32,
+    key: usize,
+}
+
+impl PartialEq for Prioritized {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Prioritized {}
+
+impl PartialOrd for Prioritized {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Prioritized {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.total_cmp(&other.priority)
+    }
+}
+
+/// Max-priority queue over keys of type K. Pushing a key that is already queued keeps the larger
+/// priority, stale heap entries are skipped when popping.
+struct PriorityQueue<K> {
+    heap: BinaryHeap<Prioritized>,
+    keys: Vec<K>,
+    index: HashMap<K, usize>,
+    queued: HashMap<usize, f32>,
+}
+
+impl<K> PriorityQueue<K>
+where
+    K: Eq + std::hash::Hash + Clone,
+{
+    fn new() -> Self {
+        PriorityQueue {
+            heap: BinaryHeap::new(),
+            keys: Vec::new(),
+            index: HashMap::new(),
+            queued: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, key: &K, priority: f32) {
+        let i = match self.index.get(key) {
+            Some(&i) => i,
+            None => {
+                self.index.insert(key.clone(), self.keys.len());
+                self.keys.push(key.clone());
+                self.keys.len() - 1
+            }
+        };
+        if self.queued.get(&i).is_some_and(|&p| p >= priority) {
+            return;
+        }
+        self.queued.insert(i, priority);
+        self.heap.push(Prioritized { priority, key: i });
+    }
+
+    fn pop(&mut self) -> Option<K> {
+        while let Some(entry) = self.heap.pop() {
+            if self.queued.get(&entry.key) == Some(&entry.priority) {
+                self.queued.remove(&entry.key);
+                return Some(self.keys[entry.key].clone());
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanningSettings {
+    pub gamma: f32,
+    /// Bellman errors below this are not queued, full sweeps stop once no value moves more.
+    pub theta: f32,
+    pub max_backups: usize,
+}
+
+impl Default for PlanningSettings {
+    fn default() -> Self {
+        PlanningSettings {
+            gamma: 1.0,
+            theta: 1e-4,
+            max_backups: 10_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanningResult<S>
+where
+    S: State,
+{
+    pub values: HashMap<S, f32>,
+    /// Number of value updates performed.
+    pub backups: usize,
+    /// Largest absolute error against the reference, recorded after every `states.len()`
+    /// backups. Empty when no reference is given.
+    pub errors: Vec<(usize, f32)>,
+}
+
+/// Expected update of the value of `state` with the best action, zero for states without
+/// actions. Missing values count as zero.
+pub fn bellman_backup<E, S, A>(env: &E, state: &S, values: &HashMap<S, f32>, gamma: f32) -> f32
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    env.posible_actions(state)
+        .iter()
+        .map(|action| {
+            env.dynamics(state, action)
+                .iter()
+                .map(|((next_state, reward), prob)| {
+                    let next_value = values.get(next_state).copied().unwrap_or(0.0);
+                    prob * (*reward as f32 + gamma * next_value)
+                })
+                .sum::<f32>()
+        })
+        .fold(None, |best: Option<f32>, v| {
+            Some(best.map_or(v, |b| b.max(v)))
+        })
+        .unwrap_or(0.0)
+}
+
+fn max_error<S>(values: &HashMap<S, f32>, reference: &HashMap<S, f32>) -> f32
+where
+    S: State,
+{
+    reference
+        .iter()
+        .map(|(state, v)| (values.get(state).copied().unwrap_or(0.0) - v).abs())
+        .fold(0.0, f32::max)
+}
+
+/// In place full sweeps over `states` in order, the same updates `value_iteration` makes but
+/// counted. With a reference it stops once every value is within `target_error` of it,
+/// otherwise once a sweep changes no value by more than `settings.theta`.
+pub fn full_sweep_planner<E, S, A>(
+    env: &E,
+    states: &[S],
+    values: HashMap<S, f32>,
+    settings: &PlanningSettings,
+    reference: Option<(&HashMap<S, f32>, f32)>,
+) -> PlanningResult<S>
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    let mut values = values;
+    let mut backups = 0;
+    let mut errors = Vec::new();
+    while backups < settings.max_backups {
+        let mut delta: f32 = 0.0;
+        for state in states {
+            if env.is_terminal(state) {
+                continue;
+            }
+            let value = bellman_backup(env, state, &values, settings.gamma);
+            let old = values.insert(state.clone(), value).unwrap_or(0.0);
+            delta = delta.max((value - old).abs());
+            backups += 1;
+        }
+        if let Some((reference, target_error)) = reference {
+            let error = max_error(&values, reference);
+            errors.push((backups, error));
+            if error < target_error {
+                break;
+            }
+        } else if delta < settings.theta {
+            break;
+        }
+    }
+    PlanningResult {
+        values,
+        backups,
+        errors,
+    }
+}
+
+/// Prioritized sweeping as a planner over a known model (Section 8.4). States are queued by the
+/// size of their Bellman error, the state with the largest error is updated first and then its
+/// predecessors are re-evaluated and queued. Stops when no error is above `settings.theta` or,
+/// with a reference, once every value is within `target_error` of it.
+pub fn prioritized_sweeping_planner<E, S, A>(
+    env: &E,
+    states: &[S],
+    values: HashMap<S, f32>,
+    settings: &PlanningSettings,
+    reference: Option<(&HashMap<S, f32>, f32)>,
+) -> PlanningResult<S>
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    let mut values = values;
+    let mut predecessors: HashMap<S, HashSet<S>> = HashMap::new();
+    let mut queue = PriorityQueue::new();
+    for state in states {
+        if env.is_terminal(state) {
+            continue;
+        }
+        for action in env.posible_actions(state) {
+            for (next_state, _) in env.dynamics(state, &action).into_keys() {
+                predecessors
+                    .entry(next_state)
+                    .or_default()
+                    .insert(state.clone());
+            }
+        }
+        let value = values.get(state).copied().unwrap_or(0.0);
+        let error = (bellman_backup(env, state, &values, settings.gamma) - value).abs();
+        if error > settings.theta {
+            queue.push(state, error);
+        }
+    }
+
+    let mut backups = 0;
+    let mut errors = Vec::new();
+    let report = states.len().max(1);
+    while let Some(state) = queue.pop() {
+        if backups >= settings.max_backups {
+            break;
+        }
+        let value = bellman_backup(env, &state, &values, settings.gamma);
+        values.insert(state.clone(), value);
+        backups += 1;
+        if let Some((reference, target_error)) = reference {
+            if backups % report == 0 {
+                let error = max_error(&values, reference);
+                errors.push((backups, error));
+                if error < target_error {
+                    break;
+                }
+            }
+        }
+        for predecessor in predecessors.get(&state).into_iter().flatten() {
+            let old = values.get(predecessor).copied().unwrap_or(0.0);
+            let error = (bellman_backup(env, predecessor, &values, settings.gamma) - old).abs();
+            if error > settings.theta {
+                queue.push(predecessor, error);
+            }
+        }
+    }
+    if let Some((reference, _)) = reference {
+        errors.push((backups, max_error(&values, reference)));
+    }
+    PlanningResult {
+        values,
+        backups,
+        errors,
+    }
+}
+
+/// Backups needed by full sweeps and by prioritized sweeping, both starting from zero, to get
+/// every value within `target_error` of `reference`.
+pub fn compare_backups<E, S, A>(
+    env: &E,
+    states: &[S],
+    reference: &HashMap<S, f32>,
+    target_error: f32,
+    settings: &PlanningSettings,
+) -> (usize, usize)
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    let zeros: HashMap<S, f32> = states.iter().map(|s| (s.clone(), 0.0)).collect();
+    let sweeps = full_sweep_planner(
+        env,
+        states,
+        zeros.clone(),
+        settings,
+        Some((reference, target_error)),
+    );
+    let prioritized = prioritized_sweeping_planner(
+        env,
+        states,
+        zeros,
+        settings,
+        Some((reference, target_error)),
+    );
+    (sweeps.backups, prioritized.backups)
+}
+
+/// Online prioritized sweeping for deterministic environments (Section 8.4). Real transitions
+/// update a tabular model, the visited pair is queued by its TD error and then up to
+/// `params.planning_steps` queued pairs are updated, each one queueing the pairs predicted to
+/// lead to its state. Pairs with errors below `theta` are not queued. `params.kappa` is not used.
+pub fn prioritized_sweeping<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &DynaParameters,
+    theta: f32,
+) -> DynaResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut model = TabularModel::new();
+    let mut predecessors: HashMap<S, HashSet<(S, A)>> = HashMap::new();
+    let mut queue = PriorityQueue::new();
+    let mut cumulative_rewards = Vec::with_capacity(params.steps);
+    let mut lengths = Vec::new();
+    let mut total = 0.0;
+    let mut state = init_states.choose(&mut rng).unwrap().clone();
+    let mut episode_length = 0;
+    for time in 0..params.steps {
+        let action = epsilon_greedy(env, &vals, &state, params.epsilon);
+        let (next_state, reward) = env.response(&state, &action);
+        total += reward as f32;
+        cumulative_rewards.push(total);
+        episode_length += 1;
+
+        model.update(
+            state.clone(),
+            action.clone(),
+            next_state.clone(),
+            reward,
+            time,
+        );
+        predecessors
+            .entry(next_state.clone())
+            .or_default()
+            .insert((state.clone(), action.clone()));
+        let pair = (state.clone(), action);
+        let error = td_error(env, &vals, &pair, &next_state, reward, params.gamma).abs();
+        if error > theta {
+            queue.push(&pair, error);
+        }
+
+        for _ in 0..params.planning_steps {
+            let Some((s, a)) = queue.pop() else {
+                break;
+            };
+            let (s_next, r) = model.outcome(&s, &a).unwrap().clone();
+            let error = td_error(
+                env,
+                &vals,
+                &(s.clone(), a.clone()),
+                &s_next,
+                r,
+                params.gamma,
+            );
+            *vals.entry((s.clone(), a)).or_insert(0.0) += params.alpha * error;
+            for predecessor in predecessors.get(&s).into_iter().flatten() {
+                let (_, r_bar) = model.outcome(&predecessor.0, &predecessor.1).unwrap();
+                let error = td_error(env, &vals, predecessor, &s, *r_bar, params.gamma).abs();
+                if error > theta {
+                    queue.push(predecessor, error);
+                }
+            }
+        }
+
+        state = if env.is_terminal(&next_state) {
+            lengths.push(episode_length);
+            episode_length = 0;
+            init_states.choose(&mut rng).unwrap().clone()
+        } else {
+            next_state
+        };
+    }
+    let policy = greedy_policy(env, &vals);
+    DynaResult {
+        action_values: vals,
+        policy,
+        cumulative_rewards,
+        lengths,
+    }
+}
+
+fn td_error<'a, E, S, A>(
+    env: &E,
+    vals: &HashMap<(S, A), f32>,
+    pair: &(S, A),
+    next_state: &S,
+    reward: i32,
+    gamma: f32,
+) -> f32
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let next_value = if env.is_terminal(next_state) {
+        0.0
+    } else {
+        max_action_value(env, vals, next_state)
+    };
+    reward as f32 + gamma * next_value - action_value(vals, &pair.0, &pair.1)
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::{thread_rng, Rng, SeedableRng};
+
+use super::mdp::{Action, Agent, EnviormentModel, Policy, State};
+use super::policy_iteration::{greedy_policy, policy_iteration, value_iteration};
+use crate::utils::stats::sample_normal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MdpState(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MdpAction(pub usize);
+
+impl State for MdpState {}
+impl Action for MdpAction {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RewardDistribution {
+    Constant(f32),
+    Uniform {
+        low: f32,
+        high: f32,
+    },
+    Normal {
+        mean: f32,
+        std_dev: f32,
+    },
+    /// `reward` with probability `p`, zero otherwise.
+    Bernoulli {
+        p: f32,
+        reward: f32,
+    },
+}
+
+impl RewardDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        match *self {
+            RewardDistribution::Constant(reward) => reward,
+            RewardDistribution::Uniform { low, high } => rng.gen_range(low..=high),
+            RewardDistribution::Normal { mean, std_dev } => sample_normal(rng, mean, std_dev),
+            RewardDistribution::Bernoulli { p, reward } => {
+                if rng.gen::<f32>() < p {
+                    reward
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RandomMdpParameters {
+    pub states: usize,
+    pub actions: usize,
+    /// Next states of every state–action pair.
+    pub branching: usize,
+    /// Probability of every transition ending the episode.
+    pub termination: f32,
+    pub rewards: RewardDistribution,
+    /// Sampled rewards are multiplied by this before rounding to integers.
+    pub reward_scale: f32,
+    /// Seed of the generator, the same seed and parameters give the same MDP. Sampling
+    /// transitions with `response` is not seeded.
+    pub seed: Option<u64>,
+}
+
+impl Default for RandomMdpParameters {
+    fn default() -> Self {
+        RandomMdpParameters {
+            states: 100,
+            actions: 2,
+            branching: 3,
+            termination: 0.1,
+            rewards: RewardDistribution::Normal {
+                mean: 0.0,
+                std_dev: 1.0,
+            },
+            reward_scale: 100.0,
+            seed: None,
+        }
+    }
+}
+
+impl RandomMdpParameters {
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    fn reward<R: Rng>(&self, rng: &mut R) -> i32 {
+        (self.rewards.sample(rng) * self.reward_scale).round() as i32
+    }
+}
+
+/// Finite MDP given by explicit tables. `transitions[s][a]` lists the (next state, reward,
+/// probability) outcomes of taking action a in state s, the state with index
+/// `transitions.len()` is the only terminal one. Rewards are integers, so generated rewards are
+/// multiplied by `reward_scale` before rounding and values should be divided by it.
+#[derive(Debug, Clone)]
+pub struct RandomMdp {
+    pub transitions: Vec<Vec<Vec<(usize, i32, f32)>>>,
+    pub start: MdpState,
+    pub reward_scale: f32,
+}
+
+impl RandomMdp {
+    /// Task of Section 8.6 with `params.states` states: from every state each of the actions
+    /// leads to one of `params.branching` next states picked at random, all equally likely, with
+    /// a reward drawn from `params.rewards` for each of them, and every transition ends the
+    /// episode with probability `params.termination`.
+    pub fn generate(params: &RandomMdpParameters) -> Self {
+        let mut rng = params.rng();
+        let terminal = params.states;
+        let transitions = (0..params.states)
+            .map(|_| {
+                (0..params.actions)
+                    .map(|_| {
+                        let outcomes = (0..params.branching)
+                            .map(|_| {
+                                (
+                                    rng.gen_range(0..params.states),
+                                    params.reward(&mut rng),
+                                    1.0 / params.branching as f32,
+                                )
+                            })
+                            .collect();
+                        with_termination(outcomes, params.termination, terminal)
+                    })
+                    .collect()
+            })
+            .collect();
+        RandomMdp {
+            transitions,
+            start: MdpState(0),
+            reward_scale: params.reward_scale,
+        }
+    }
+
+    /// The tasks of Section 8.6, N(0, 1) rewards and a different random task on every call.
+    pub fn branching(
+        states: usize,
+        actions: usize,
+        branching: usize,
+        termination: f32,
+        reward_scale: f32,
+    ) -> Self {
+        RandomMdp::generate(&RandomMdpParameters {
+            states,
+            actions,
+            branching,
+            termination,
+            reward_scale,
+            ..Default::default()
+        })
+    }
+
+    /// Garnet MDP (Archibald, McKinnon and Thomas): every state–action pair leads to
+    /// `params.branching` distinct next states with probabilities given by a uniformly random
+    /// partition of the unit interval, and has a single reward drawn from `params.rewards`. With
+    /// no termination the task is continuing and needs γ < 1.
+    pub fn garnet(params: &RandomMdpParameters) -> Self {
+        let mut rng = params.rng();
+        let terminal = params.states;
+        let branching = params.branching.clamp(1, params.states);
+        let transitions = (0..params.states)
+            .map(|_| {
+                (0..params.actions)
+                    .map(|_| {
+                        let mut cuts: Vec<f32> = (1..branching).map(|_| rng.gen()).collect();
+                        cuts.push(0.0);
+                        cuts.push(1.0);
+                        cuts.sort_by(f32::total_cmp);
+                        let reward = params.reward(&mut rng);
+                        let outcomes = index::sample(&mut rng, params.states, branching)
+                            .into_iter()
+                            .zip(cuts.windows(2))
+                            .map(|(next_state, cut)| (next_state, reward, cut[1] - cut[0]))
+                            .collect();
+                        with_termination(outcomes, params.termination, terminal)
+                    })
+                    .collect()
+            })
+            .collect();
+        RandomMdp {
+            transitions,
+            start: MdpState(0),
+            reward_scale: params.reward_scale,
+        }
+    }
+
+    /// Chain of `params.states` states with two actions. Forward moves one state along, and off
+    /// the end of the chain into the terminal state, except with probability `slip` where it
+    /// falls back to the first state. Back always returns to the first state. Every transition
+    /// has its own reward drawn from `params.rewards`.
+    pub fn chain(params: &RandomMdpParameters, slip: f32) -> Self {
+        let mut rng = params.rng();
+        let terminal = params.states;
+        let transitions = (0..params.states)
+            .map(|state| {
+                let mut forward = vec![(state + 1, params.reward(&mut rng), 1.0 - slip)];
+                if slip > 0.0 {
+                    forward.push((0, params.reward(&mut rng), slip));
+                }
+                let back = vec![(0, params.reward(&mut rng), 1.0)];
+                vec![
+                    with_termination(forward, params.termination, terminal),
+                    with_termination(back, params.termination, terminal),
+                ]
+            })
+            .collect();
+        RandomMdp {
+            transitions,
+            start: MdpState(0),
+            reward_scale: params.reward_scale,
+        }
+    }
+
+    /// Tree of the given depth rooted at the start state: every action of a node leads to
+    /// `params.branching` new children, all equally likely, and every action of a leaf ends the
+    /// episode. Each transition has its own reward drawn from `params.rewards`. `params.states`
+    /// and `params.termination` are not used.
+    pub fn tree(params: &RandomMdpParameters, depth: u32) -> Self {
+        let mut rng = params.rng();
+        let width = params.actions * params.branching;
+        let states: usize = (0..=depth).map(|level| width.pow(level)).sum();
+        let internal = states - width.pow(depth);
+        let prob = 1.0 / params.branching as f32;
+        let transitions = (0..states)
+            .map(|state| {
+                (0..params.actions)
+                    .map(|action| {
+                        if state >= internal {
+                            return vec![(states, params.reward(&mut rng), 1.0)];
+                        }
+                        (0..params.branching)
+                            .map(|child| {
+                                let next_state =
+                                    state * width + action * params.branching + child + 1;
+                                (next_state, params.reward(&mut rng), prob)
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        RandomMdp {
+            transitions,
+            start: MdpState(0),
+            reward_scale: params.reward_scale,
+        }
+    }
+
+    pub fn terminal(&self) -> MdpState {
+        MdpState(self.transitions.len())
+    }
+
+    /// Nonterminal states.
+    pub fn nonterminal_states(&self) -> Vec<MdpState> {
+        (0..self.transitions.len()).map(MdpState).collect()
+    }
+}
+
+/// Scales the outcomes down and adds a transition to `terminal` with probability `termination`.
+fn with_termination(
+    outcomes: Vec<(usize, i32, f32)>,
+    termination: f32,
+    terminal: usize,
+) -> Vec<(usize, i32, f32)> {
+    if termination <= 0.0 {
+        return outcomes;
+    }
+    let mut outcomes: Vec<(usize, i32, f32)> = outcomes
+        .into_iter()
+        .map(|(next_state, reward, prob)| (next_state, reward, prob * (1.0 - termination)))
+        .collect();
+    outcomes.push((terminal, 0, termination));
+    outcomes
+}
+
+impl EnviormentModel<MdpState, MdpAction> for RandomMdp {
+    fn dynamics(&self, state: &MdpState, action: &MdpAction) -> HashMap<(MdpState, i32), f32> {
+        let mut distribution = HashMap::new();
+        let Some(actions) = self.transitions.get(state.0) else {
+            return distribution;
+        };
+        for &(next_state, reward, prob) in &actions[action.0] {
+            *distribution
+                .entry((MdpState(next_state), reward))
+                .or_insert(0.0) += prob;
+        }
+        distribution
+    }
+    /// Every action, also in the terminal state where none has any outcome.
+    fn posible_actions(&self, state: &MdpState) -> Vec<MdpAction> {
+        let actions = self.transitions.first().map_or(0, |actions| actions.len());
+        (0..actions).map(MdpAction).collect()
+    }
+    fn get_states(&self) -> Vec<MdpState> {
+        (0..=self.transitions.len()).map(MdpState).collect()
+    }
+    fn response(&self, state: &MdpState, action: &MdpAction) -> (MdpState, i32) {
+        let outcomes = &self.transitions[state.0][action.0];
+        let mut cutoff = thread_rng().gen_range(0.0..1.0);
+        for &(next_state, reward, prob) in outcomes {
+            if cutoff < prob {
+                return (MdpState(next_state), reward);
+            }
+            cutoff -= prob;
+        }
+        let &(next_state, reward, _) = outcomes.last().unwrap();
+        (MdpState(next_state), reward)
+    }
+    fn is_terminal(&self, state: &MdpState) -> bool {
+        state.0 >= self.transitions.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolverComparison {
+    /// Largest difference between the state values found by the two solvers.
+    pub max_difference: f32,
+    /// Fraction of nonterminal states where the greedy policies of both value functions agree.
+    pub policy_agreement: f32,
+    pub value_iteration_time: Duration,
+    pub policy_iteration_time: Duration,
+}
+
+/// Solves `mdp` with `value_iteration` and with `policy_iteration` from zero values and compares
+/// the results and the time each took.
+pub fn compare_solvers(mdp: &RandomMdp, gamma: f32, tolerance: f32) -> SolverComparison {
+    let states = mdp.get_states();
+    let zeros: HashMap<MdpState, f32> = states.iter().map(|s| (*s, 0.0)).collect();
+
+    let start = Instant::now();
+    let iterated = value_iteration(mdp, &states, Some(zeros.clone()), gamma, tolerance);
+    let value_iteration_time = start.elapsed();
+
+    let mut agent = Agent {
+        policy: Policy::Deterministic(states.iter().map(|s| (*s, MdpAction(0))).collect()),
+    };
+    let start = Instant::now();
+    let evaluated = policy_iteration(&mut agent, mdp, Some(zeros), gamma, tolerance);
+    let policy_iteration_time = start.elapsed();
+
+    let max_difference = states
+        .iter()
+        .map(|s| (iterated[s] - evaluated[s]).abs())
+        .fold(0.0, f32::max);
+    let nonterminal = mdp.nonterminal_states();
+    let first = greedy_policy(mdp, &nonterminal, &iterated, gamma);
+    let second = greedy_policy(mdp, &nonterminal, &evaluated, gamma);
+    let agreeing = nonterminal.iter().filter(|s| first[s] == second[s]).count();
+    SolverComparison {
+        max_difference,
+        policy_agreement: agreeing as f32 / nonterminal.len().max(1) as f32,
+        value_iteration_time,
+        policy_iteration_time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-3;
+    /// Rewards are scaled by 100, so this is a thousandth of a unit reward.
+    const MAX_DIFFERENCE: f32 = 0.1;
+
+    fn params(seed: u64, termination: f32) -> RandomMdpParameters {
+        RandomMdpParameters {
+            states: 50,
+            termination,
+            seed: Some(seed),
+            ..Default::default()
+        }
+    }
+
+    fn assert_solvers_agree(mdp: &RandomMdp, gamma: f32) {
+        let comparison = compare_solvers(mdp, gamma, TOLERANCE);
+        assert!(
+            comparison.max_difference < MAX_DIFFERENCE,
+            "values differ by {}",
+            comparison.max_difference
+        );
+        assert_eq!(comparison.policy_agreement, 1.0);
+    }
+
+    #[test]
+    fn solvers_agree_on_branching_mdps() {
+        for seed in 0..5 {
+            assert_solvers_agree(&RandomMdp::generate(&params(seed, 0.1)), 1.0);
+        }
+    }
+
+    #[test]
+    fn solvers_agree_on_garnets() {
+        for seed in 0..5 {
+            assert_solvers_agree(&RandomMdp::garnet(&params(seed, 0.0)), 0.9);
+        }
+    }
+
+    #[test]
+    fn solvers_agree_on_chains() {
+        for seed in 0..5 {
+            assert_solvers_agree(&RandomMdp::chain(&params(seed, 0.0), 0.2), 0.9);
+        }
+    }
+
+    #[test]
+    fn solvers_agree_on_trees() {
+        for seed in 0..5 {
+            assert_solvers_agree(&RandomMdp::tree(&params(seed, 0.1), 3), 1.0);
+        }
+    }
+}
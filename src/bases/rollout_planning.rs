@@ -0,0 +1,263 @@
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mcts::Mcts;
+use super::mdp::{Action, Enviorment, Policy, State};
+
+/// Planner that picks an action for the current state only, simulating with the environment.
+pub trait DecisionPlanner<'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    fn act<E>(&mut self, env: &E, state: &S) -> A
+    where
+        E: Enviorment<'a, S, A>;
+}
+
+impl<'p, 'a, S, A> DecisionPlanner<'a, S, A> for Mcts<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    fn act<E>(&mut self, env: &E, state: &S) -> A
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        Mcts::act(self, env, state)
+    }
+}
+
+/// Discounted return of following `pol` from `state` for at most `depth` steps, and the number of
+/// steps taken before a terminal state or the depth cut.
+pub fn policy_rollout<'a, E, S, A>(
+    env: &E,
+    pol: &Policy<'a, S, A>,
+    state: &S,
+    depth: usize,
+    gamma: f32,
+) -> (f32, usize)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut state = state.clone();
+    let mut g = 0.0;
+    let mut discount = 1.0;
+    let mut steps = 0;
+    while steps < depth && !env.is_terminal(&state) {
+        let action = pol.sample_action(&state);
+        let (next_state, reward) = env.response(&state, &action);
+        g += discount * reward as f32;
+        discount *= gamma;
+        state = next_state;
+        steps += 1;
+    }
+    (g, steps)
+}
+
+/// Rollout algorithm (Section 8.10). Every action of the current state is estimated by the
+/// average return of `rollouts_per_action` simulations that take it and then follow `base`, and
+/// the best one is taken, a step of policy improvement over `base` made only where it is needed.
+pub struct RolloutPlanner<'p, 'a, S, A> {
+    pub base: &'p Policy<'a, S, A>,
+    pub rollouts_per_action: usize,
+    /// Steps of every rollout after the first action.
+    pub depth: usize,
+    pub gamma: f32,
+    /// Rollouts performed since the planner was created.
+    pub rollouts: usize,
+}
+
+impl<'p, 'a, S, A> RolloutPlanner<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn new(
+        base: &'p Policy<'a, S, A>,
+        rollouts_per_action: usize,
+        depth: usize,
+        gamma: f32,
+    ) -> Self {
+        assert!(
+            rollouts_per_action > 0,
+            "every action needs at least one rollout to be estimated"
+        );
+        RolloutPlanner {
+            base,
+            rollouts_per_action,
+            depth,
+            gamma,
+            rollouts: 0,
+        }
+    }
+}
+
+impl<'p, 'a, S, A> DecisionPlanner<'a, S, A> for RolloutPlanner<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    fn act<E>(&mut self, env: &E, state: &S) -> A
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        let mut best = None;
+        let mut best_value = f32::NEG_INFINITY;
+        for action in env.posible_actions(state) {
+            let mut total = 0.0;
+            for _ in 0..self.rollouts_per_action {
+                let (next_state, reward) = env.response(state, &action);
+                total += reward as f32
+                    + self.gamma
+                        * policy_rollout(env, self.base, &next_state, self.depth, self.gamma).0;
+            }
+            self.rollouts += self.rollouts_per_action;
+            let value = total / self.rollouts_per_action as f32;
+            if value > best_value {
+                best_value = value;
+                best = Some(action);
+            }
+        }
+        best.unwrap()
+    }
+}
+
+/// Sparse sampling (Kearns, Mansour and Ng). Action values are estimated recursively from
+/// `width` sampled transitions per action down to `depth`, so the cost does not depend on the
+/// number of states but grows as (actions · width)^depth. Leaves are worth zero, or the return of
+/// a rollout of `leaf_policy` when one is given.
+pub struct SparseSampling<'p, 'a, S, A> {
+    pub depth: usize,
+    pub width: usize,
+    pub gamma: f32,
+    pub leaf_policy: Option<&'p Policy<'a, S, A>>,
+    pub leaf_depth: usize,
+    /// Transitions sampled from the environment since the planner was created, leaf rollouts
+    /// included.
+    pub samples: usize,
+    /// Leaf rollouts performed since the planner was created.
+    pub rollouts: usize,
+}
+
+impl<'p, 'a, S, A> SparseSampling<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn new(depth: usize, width: usize, gamma: f32) -> Self {
+        assert!(
+            width > 0,
+            "every action needs at least one sampled transition"
+        );
+        SparseSampling {
+            depth,
+            width,
+            gamma,
+            leaf_policy: None,
+            leaf_depth: 0,
+            samples: 0,
+            rollouts: 0,
+        }
+    }
+
+    /// Evaluates the leaves with rollouts of `pol` of at most `depth` steps.
+    pub fn with_leaf_policy(mut self, pol: &'p Policy<'a, S, A>, depth: usize) -> Self {
+        self.leaf_policy = Some(pol);
+        self.leaf_depth = depth;
+        self
+    }
+
+    fn action_values<E>(&mut self, env: &E, state: &S, depth: usize) -> Vec<(A, f32)>
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        env.posible_actions(state)
+            .into_iter()
+            .map(|action| {
+                let mut total = 0.0;
+                for _ in 0..self.width {
+                    let (next_state, reward) = env.response(state, &action);
+                    self.samples += 1;
+                    total += reward as f32 + self.gamma * self.value(env, &next_state, depth - 1);
+                }
+                (action, total / self.width as f32)
+            })
+            .collect()
+    }
+
+    fn value<E>(&mut self, env: &E, state: &S, depth: usize) -> f32
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        if env.is_terminal(state) {
+            return 0.0;
+        }
+        if depth == 0 {
+            return match self.leaf_policy {
+                Some(pol) => {
+                    let (g, steps) = policy_rollout(env, pol, state, self.leaf_depth, self.gamma);
+                    self.rollouts += 1;
+                    self.samples += steps;
+                    g
+                }
+                None => 0.0,
+            };
+        }
+        self.action_values(env, state, depth)
+            .into_iter()
+            .map(|(_, v)| v)
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+}
+
+impl<'p, 'a, S, A> DecisionPlanner<'a, S, A> for SparseSampling<'p, 'a, S, A>
+where
+    S: State,
+    A: Action,
+{
+    fn act<E>(&mut self, env: &E, state: &S) -> A
+    where
+        E: Enviorment<'a, S, A>,
+    {
+        let values = self.action_values(env, state, self.depth.max(1));
+        let max = values
+            .iter()
+            .map(|(_, v)| *v)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let best: Vec<&A> = values
+            .iter()
+            .filter(|(_, v)| *v == max)
+            .map(|(a, _)| a)
+            .collect();
+        (*best.choose(&mut thread_rng()).unwrap()).clone()
+    }
+}
+
+/// Plays an episode from `init_state` choosing every action with `planner`, returns the
+/// trajectory and whether it reached a terminal state within `max_steps`.
+pub fn planned_episode<'a, E, S, A, P>(
+    env: &E,
+    planner: &mut P,
+    init_state: &S,
+    max_steps: usize,
+) -> (Vec<(S, A, i32)>, bool)
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    P: DecisionPlanner<'a, S, A>,
+{
+    let mut state = init_state.clone();
+    let mut trajectory = Vec::new();
+    while !env.is_terminal(&state) && trajectory.len() < max_steps {
+        let action = planner.act(env, &state);
+        let (next_state, reward) = env.response(&state, &action);
+        trajectory.push((state, action, reward));
+        state = next_state;
+    }
+    let terminated = env.is_terminal(&state);
+    (trajectory, terminated)
+}
@@ -0,0 +1,297 @@
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use super::function_approximation::{ActionValueFunction, FeatureExtractor, ValueFunction};
+use super::mdp::{Action, Enviorment, Policy, State};
+use super::n_step::discounted_rewards;
+use super::td_control::TdParameters;
+use crate::utils::ring_buffer::RingBuffer;
+
+/// Gradient Monte Carlo prediction (Section 9.3), every visited state is moved towards its full
+/// return, a true gradient step on the value error of the sampled state.
+#[allow(clippy::too_many_arguments)]
+pub fn gradient_monte_carlo<'a, E, S, A, F, V>(
+    pol: &Policy<'a, S, A>,
+    approximation: V,
+    features: &F,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    alpha: f32,
+    gamma: f32,
+) -> V
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+    V: ValueFunction,
+{
+    let mut rng = thread_rng();
+    let mut approximation = approximation;
+    for _ in 0..episodes {
+        let init_state = init_states.choose(&mut rng).unwrap();
+        let trajectory = env.episode(init_state, pol);
+        let mut g = 0.0;
+        for (state, _, reward) in trajectory.iter().rev() {
+            g = *reward as f32 + gamma * g;
+            let x = features.features(state);
+            let error = g - approximation.value(&x);
+            approximation.update(&x, alpha * error);
+        }
+    }
+    approximation
+}
+
+/// Semi-gradient TD(0) prediction (Section 9.3), the bootstrapped target R + γ v̂(S') is treated
+/// as fixed when taking the gradient. Terminal states are worth zero.
+#[allow(clippy::too_many_arguments)]
+pub fn semi_gradient_td_zero<'a, E, S, A, F, V>(
+    pol: &Policy<'a, S, A>,
+    approximation: V,
+    features: &F,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    alpha: f32,
+    gamma: f32,
+) -> V
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+    V: ValueFunction,
+{
+    let mut rng = thread_rng();
+    let mut approximation = approximation;
+    for _ in 0..episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut x = features.features(&state);
+        while !env.is_terminal(&state) {
+            let action = pol.sample_action(&state);
+            let (next_state, reward) = env.response(&state, &action);
+            let next_x = features.features(&next_state);
+            let next_value = if env.is_terminal(&next_state) {
+                0.0
+            } else {
+                approximation.value(&next_x)
+            };
+            let error = reward as f32 + gamma * next_value - approximation.value(&x);
+            approximation.update(&x, alpha * error);
+            state = next_state;
+            x = next_x;
+        }
+    }
+    approximation
+}
+
+/// n-step semi-gradient TD prediction (Section 9.4), `n_step_td_prediction` with the table
+/// replaced by the approximation. The features of the last n + 1 states are kept with their
+/// rewards.
+#[allow(clippy::too_many_arguments)]
+pub fn semi_gradient_n_step_td<'a, E, S, A, F, V>(
+    pol: &Policy<'a, S, A>,
+    approximation: V,
+    features: &F,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    n: usize,
+    alpha: f32,
+    gamma: f32,
+) -> V
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+    V: ValueFunction,
+{
+    let mut rng = thread_rng();
+    let mut approximation = approximation;
+    let mut buffer: RingBuffer<(Vec<f32>, i32)> = RingBuffer::new(n + 1);
+    for _ in 0..episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            continue;
+        }
+        buffer.clear();
+        let mut end = usize::MAX;
+        let mut t = 0;
+        loop {
+            if t < end {
+                let action = pol.sample_action(&state);
+                let (next_state, reward) = env.response(&state, &action);
+                buffer.set(t, (features.features(&state), reward));
+                state = next_state;
+                if env.is_terminal(&state) {
+                    end = t + 1;
+                }
+            }
+            if t + 1 >= n {
+                let tau = t + 1 - n;
+                let mut g = discounted_rewards(&buffer, tau, (tau + n).min(end), gamma, |x| x.1);
+                if tau + n < end {
+                    g += gamma.powi(n as i32) * approximation.value(&features.features(&state));
+                }
+                let x = &buffer.get(tau).0;
+                let error = g - approximation.value(x);
+                approximation.update(x, alpha * error);
+                if tau + 1 == end {
+                    break;
+                }
+            }
+            t += 1;
+        }
+    }
+    approximation
+}
+
+#[derive(Debug, Clone)]
+pub struct ApproximateControlResult<Q> {
+    pub approximation: Q,
+    /// Undiscounted sum of rewards of every episode.
+    pub returns: Vec<f32>,
+    pub lengths: Vec<usize>,
+}
+
+/// Action of `state` with the largest approximate value, ties broken at random.
+pub fn approximate_greedy_action<'a, E, S, A, Q>(env: &E, q: &Q, features: &[f32], state: &S) -> A
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    Q: ActionValueFunction<A>,
+{
+    let actions = env.posible_actions(state);
+    let values: Vec<f32> = actions.iter().map(|a| q.value(features, a)).collect();
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let best: Vec<&A> = actions
+        .iter()
+        .zip(&values)
+        .filter(|(_, &v)| v == max)
+        .map(|(a, _)| a)
+        .collect();
+    (*best.choose(&mut thread_rng()).unwrap()).clone()
+}
+
+pub fn approximate_epsilon_greedy<'a, E, S, A, Q>(
+    env: &E,
+    q: &Q,
+    features: &[f32],
+    state: &S,
+    epsilon: f32,
+) -> A
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    Q: ActionValueFunction<A>,
+{
+    let mut rng = thread_rng();
+    if rng.gen::<f32>() < epsilon {
+        env.posible_actions(state).choose(&mut rng).unwrap().clone()
+    } else {
+        approximate_greedy_action(env, q, features, state)
+    }
+}
+
+/// Episodic semi-gradient one-step Sarsa (Section 10.1).
+pub fn semi_gradient_sarsa<'a, E, S, A, F, Q>(
+    env: &E,
+    approximation: Q,
+    features: &F,
+    init_states: &[S],
+    params: &TdParameters,
+) -> ApproximateControlResult<Q>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+    Q: ActionValueFunction<A>,
+{
+    semi_gradient_n_step_sarsa(env, approximation, features, init_states, params, 1)
+}
+
+/// Episodic semi-gradient n-step Sarsa (Section 10.2) following the ε-greedy policy of the
+/// current approximation. Episodes cut at `params.max_steps` bootstrap from the last pair.
+pub fn semi_gradient_n_step_sarsa<'a, E, S, A, F, Q>(
+    env: &E,
+    approximation: Q,
+    features: &F,
+    init_states: &[S],
+    params: &TdParameters,
+    n: usize,
+) -> ApproximateControlResult<Q>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+    F: FeatureExtractor<S> + ?Sized,
+    Q: ActionValueFunction<A>,
+{
+    let mut rng = thread_rng();
+    let mut q = approximation;
+    let mut buffer: RingBuffer<(Vec<f32>, A, i32)> = RingBuffer::new(n + 1);
+    let mut returns = Vec::with_capacity(params.episodes as usize);
+    let mut lengths = Vec::with_capacity(params.episodes as usize);
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        if env.is_terminal(&state) {
+            // Keeps one entry per episode, an episode that starts terminal has no steps.
+            returns.push(0.0);
+            lengths.push(0);
+            continue;
+        }
+        buffer.clear();
+        let mut x = features.features(&state);
+        let mut action = approximate_epsilon_greedy(env, &q, &x, &state, params.epsilon);
+        let mut end = usize::MAX;
+        let mut truncated = false;
+        let mut total = 0.0;
+        let mut t = 0;
+        loop {
+            if t < end {
+                let (next_state, reward) = env.response(&state, &action);
+                total += reward as f32;
+                buffer.set(t, (x.clone(), action.clone(), reward));
+                state = next_state;
+                if env.is_terminal(&state) {
+                    end = t + 1;
+                } else {
+                    x = features.features(&state);
+                    action = approximate_epsilon_greedy(env, &q, &x, &state, params.epsilon);
+                    if t + 1 >= params.max_steps {
+                        end = t + 1;
+                        truncated = true;
+                    }
+                }
+            }
+            if t + 1 >= n {
+                let tau = t + 1 - n;
+                let last = (tau + n).min(end);
+                let mut g = discounted_rewards(&buffer, tau, last, params.gamma, |x| x.2);
+                if tau + n < end || truncated {
+                    // The pair at `last` is always the one held in x and action.
+                    g += params.gamma.powi((last - tau) as i32) * q.value(&x, &action);
+                }
+                let (tau_x, tau_action, _) = buffer.get(tau);
+                let error = g - q.value(tau_x, tau_action);
+                q.update(tau_x, tau_action, params.alpha * error);
+                if tau + 1 == end {
+                    break;
+                }
+            }
+            t += 1;
+        }
+        returns.push(total);
+        lengths.push(end);
+    }
+    ApproximateControlResult {
+        approximation: q,
+        returns,
+        lengths,
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use super::mdp::{Action, Enviorment, State};
+use super::monte_carlo_control::greedy_action;
+
+#[derive(Debug, Clone)]
+pub struct TdParameters {
+    pub alpha: f32,
+    pub gamma: f32,
+    pub epsilon: f32,
+    pub episodes: u32,
+    /// Episodes are cut after this many steps, the last update still bootstraps.
+    pub max_steps: usize,
+}
+
+impl Default for TdParameters {
+    fn default() -> Self {
+        TdParameters {
+            alpha: 0.5,
+            gamma: 1.0,
+            epsilon: 0.1,
+            episodes: 500,
+            max_steps: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub action_values: HashMap<(S, A), f32>,
+    pub policy: HashMap<S, A>,
+    /// Undiscounted sum of rewards of every episode.
+    pub returns: Vec<f32>,
+    pub lengths: Vec<usize>,
+}
+
+/// Records, for every episode, the fraction of the visits to `state` in which `action` was
+/// taken (zero when the state was not visited), e.g. the left actions from A in Figure 6.5.
+#[derive(Debug, Clone)]
+pub struct ActionTracker<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub state: S,
+    pub action: A,
+    pub fractions: Vec<f32>,
+    visits: u32,
+    hits: u32,
+}
+
+impl<S, A> ActionTracker<S, A>
+where
+    S: State,
+    A: Action,
+{
+    pub fn new(state: S, action: A) -> Self {
+        ActionTracker {
+            state,
+            action,
+            fractions: Vec::new(),
+            visits: 0,
+            hits: 0,
+        }
+    }
+
+    pub fn record(&mut self, state: &S, action: &A) {
+        if *state == self.state {
+            self.visits += 1;
+            if *action == self.action {
+                self.hits += 1;
+            }
+        }
+    }
+
+    pub fn end_episode(&mut self) {
+        let fraction = if self.visits == 0 {
+            0.0
+        } else {
+            self.hits as f32 / self.visits as f32
+        };
+        self.fractions.push(fraction);
+        self.visits = 0;
+        self.hits = 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TdTarget {
+    Sarsa,
+    QLearning,
+    ExpectedSarsa,
+}
+
+pub fn sarsa<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    tracker: Option<&mut ActionTracker<S, A>>,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    td_control(
+        env,
+        init_vals,
+        init_states,
+        params,
+        TdTarget::Sarsa,
+        tracker,
+    )
+}
+
+pub fn q_learning<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    tracker: Option<&mut ActionTracker<S, A>>,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    td_control(
+        env,
+        init_vals,
+        init_states,
+        params,
+        TdTarget::QLearning,
+        tracker,
+    )
+}
+
+pub fn expected_sarsa<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    tracker: Option<&mut ActionTracker<S, A>>,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    td_control(
+        env,
+        init_vals,
+        init_states,
+        params,
+        TdTarget::ExpectedSarsa,
+        tracker,
+    )
+}
+
+fn td_control<'a, E, S, A>(
+    env: &E,
+    init_vals: HashMap<(S, A), f32>,
+    init_states: &[S],
+    params: &TdParameters,
+    target: TdTarget,
+    mut tracker: Option<&mut ActionTracker<S, A>>,
+) -> TdControlResult<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut vals = init_vals;
+    let mut returns = Vec::new();
+    let mut lengths = Vec::new();
+    for _ in 0..params.episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        let mut action = epsilon_greedy(env, &vals, &state, params.epsilon);
+        let mut total = 0.0;
+        let mut steps = 0;
+        while !env.is_terminal(&state) && steps < params.max_steps {
+            if let Some(tracker) = tracker.as_deref_mut() {
+                tracker.record(&state, &action);
+            }
+            let (next_state, reward) = env.response(&state, &action);
+            total += reward as f32;
+            steps += 1;
+            if env.is_terminal(&next_state) {
+                let val = vals.entry((state, action)).or_insert(0.0);
+                *val += params.alpha * (reward as f32 - *val);
+                break;
+            }
+            let next_action = epsilon_greedy(env, &vals, &next_state, params.epsilon);
+            let next_value = match target {
+                TdTarget::Sarsa => action_value(&vals, &next_state, &next_action),
+                TdTarget::QLearning => max_action_value(env, &vals, &next_state),
+                TdTarget::ExpectedSarsa => {
+                    expected_action_value(env, &vals, &next_state, params.epsilon)
+                }
+            };
+            let val = vals.entry((state, action)).or_insert(0.0);
+            *val += params.alpha * (reward as f32 + params.gamma * next_value - *val);
+            state = next_state;
+            action = next_action;
+        }
+        returns.push(total);
+        lengths.push(steps);
+        if let Some(tracker) = tracker.as_deref_mut() {
+            tracker.end_episode();
+        }
+    }
+    let policy = greedy_policy(env, &vals);
+    TdControlResult {
+        action_values: vals,
+        policy,
+        returns,
+        lengths,
+    }
+}
+
+/// Value of the pair, pairs never updated are worth zero.
+pub fn action_value<S, A>(action_values: &HashMap<(S, A), f32>, state: &S, action: &A) -> f32
+where
+    S: State,
+    A: Action,
+{
+    action_values
+        .get(&(state.clone(), action.clone()))
+        .copied()
+        .unwrap_or(0.0)
+}
+
+pub fn max_action_value<'a, E, S, A>(
+    env: &E,
+    action_values: &HashMap<(S, A), f32>,
+    state: &S,
+) -> f32
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    env.posible_actions(state)
+        .iter()
+        .map(|action| action_value(action_values, state, action))
+        .fold(f32::NEG_INFINITY, f32::max)
+}
+
+/// Expected value of the next pair under the ε-greedy policy derived from `action_values`.
+pub fn expected_action_value<'a, E, S, A>(
+    env: &E,
+    action_values: &HashMap<(S, A), f32>,
+    state: &S,
+    epsilon: f32,
+) -> f32
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    epsilon_greedy_probabilities(env, action_values, state, epsilon)
+        .iter()
+        .map(|(action, prob)| prob * action_value(action_values, state, action))
+        .sum()
+}
+
+/// Action probabilities of the ε-greedy policy derived from `action_values`, ties for the
+/// greedy action share its probability.
+pub fn epsilon_greedy_probabilities<'a, E, S, A>(
+    env: &E,
+    action_values: &HashMap<(S, A), f32>,
+    state: &S,
+    epsilon: f32,
+) -> Vec<(A, f32)>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let actions = env.posible_actions(state);
+    let values: Vec<f32> = actions
+        .iter()
+        .map(|action| action_value(action_values, state, action))
+        .collect();
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let n_greedy = values.iter().filter(|&&v| v == max).count() as f32;
+    let n = actions.len() as f32;
+    actions
+        .into_iter()
+        .zip(values)
+        .map(|(action, v)| {
+            let greedy_prob = if v == max {
+                (1.0 - epsilon) / n_greedy
+            } else {
+                0.0
+            };
+            (action, epsilon / n + greedy_prob)
+        })
+        .collect()
+}
+
+pub fn epsilon_greedy<'a, E, S, A>(
+    env: &E,
+    action_values: &HashMap<(S, A), f32>,
+    state: &S,
+    epsilon: f32,
+) -> A
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    if rng.gen::<f32>() < epsilon {
+        env.posible_actions(state).choose(&mut rng).unwrap().clone()
+    } else {
+        greedy_action(env, action_values, state)
+    }
+}
+
+/// Greedy policy over every state that appears in `action_values`.
+pub fn greedy_policy<'a, E, S, A>(env: &E, action_values: &HashMap<(S, A), f32>) -> HashMap<S, A>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut policy = HashMap::new();
+    for (state, _) in action_values.keys() {
+        if !policy.contains_key(state) {
+            policy.insert(state.clone(), greedy_action(env, action_values, state));
+        }
+    }
+    policy
+}
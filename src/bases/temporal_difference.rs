@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::mdp::{Action, Enviorment, Policy, State};
+
+/// Tabular TD(0) prediction, states missing from `init_values` start at zero and terminal states
+/// are always worth zero.
+pub fn td_zero_prediction<'a, E, S, A>(
+    pol: &Policy<'a, S, A>,
+    init_values: HashMap<S, f32>,
+    init_states: &[S],
+    episodes: u32,
+    env: &E,
+    alpha: f32,
+    gamma: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+    E: Enviorment<'a, S, A>,
+{
+    let mut rng = thread_rng();
+    let mut values = init_values;
+    for _ in 0..episodes {
+        let mut state = init_states.choose(&mut rng).unwrap().clone();
+        while !env.is_terminal(&state) {
+            let action = pol.sample_action(&state);
+            let (next_state, reward) = env.response(&state, &action);
+            let next_value = if env.is_terminal(&next_state) {
+                0.0
+            } else {
+                values.get(&next_state).copied().unwrap_or(0.0)
+            };
+            let value = values.entry(state).or_insert(0.0);
+            *value += alpha * (reward as f32 + gamma * next_value - *value);
+            state = next_state;
+        }
+    }
+    values
+}
+
+/// Batch TD(0): the increments of every transition in `trajectories` are summed with the values
+/// held fixed and only then applied, sweeping until the largest change is below `tolerance`.
+/// Every trajectory is assumed to end in a terminal state.
+pub fn batch_td_zero<S, A>(
+    trajectories: &[Vec<(S, A, i32)>],
+    init_values: HashMap<S, f32>,
+    alpha: f32,
+    gamma: f32,
+    tolerance: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+{
+    let mut values = init_values;
+    loop {
+        let mut increments: HashMap<S, f32> = HashMap::new();
+        for trajectory in trajectories {
+            for (t, (state, _, reward)) in trajectory.iter().enumerate() {
+                let next_value = match trajectory.get(t + 1) {
+                    Some((next_state, _, _)) => values.get(next_state).copied().unwrap_or(0.0),
+                    None => 0.0,
+                };
+                let value = values.get(state).copied().unwrap_or(0.0);
+                *increments.entry(state.clone()).or_insert(0.0) +=
+                    alpha * (*reward as f32 + gamma * next_value - value);
+            }
+        }
+        if apply_increments(&mut values, increments) < tolerance {
+            break;
+        }
+    }
+    values
+}
+
+/// Batch constant-α Monte Carlo, converges to the average return observed from each state.
+pub fn batch_monte_carlo<S, A>(
+    trajectories: &[Vec<(S, A, i32)>],
+    init_values: HashMap<S, f32>,
+    alpha: f32,
+    gamma: f32,
+    tolerance: f32,
+) -> HashMap<S, f32>
+where
+    S: State,
+    A: Action,
+{
+    let mut returns = Vec::new();
+    for trajectory in trajectories {
+        let mut g = 0.0;
+        let mut episode_returns = Vec::new();
+        for (state, _, reward) in trajectory.iter().rev() {
+            g = gamma * g + *reward as f32;
+            episode_returns.push((state.clone(), g));
+        }
+        returns.extend(episode_returns);
+    }
+    let mut values = init_values;
+    loop {
+        let mut increments: HashMap<S, f32> = HashMap::new();
+        for (state, g) in &returns {
+            let value = values.get(state).copied().unwrap_or(0.0);
+            *increments.entry(state.clone()).or_insert(0.0) += alpha * (g - value);
+        }
+        if apply_increments(&mut values, increments) < tolerance {
+            break;
+        }
+    }
+    values
+}
+
+fn apply_increments<S>(values: &mut HashMap<S, f32>, increments: HashMap<S, f32>) -> f32
+where
+    S: State,
+{
+    let mut delta: f32 = 0.0;
+    for (state, increment) in increments {
+        *values.entry(state).or_insert(0.0) += increment;
+        delta = delta.max(increment.abs());
+    }
+    delta
+}
+
+/// Root mean squared error of `values` against `reference` over the given states.
+pub fn rms_error<S>(values: &HashMap<S, f32>, reference: &HashMap<S, f32>, states: &[S]) -> f32
+where
+    S: State,
+{
+    let sum: f32 = states
+        .iter()
+        .map(|state| {
+            let v = values.get(state).copied().unwrap_or(0.0);
+            (v - reference[state]).powi(2)
+        })
+        .sum();
+    (sum / states.len() as f32).sqrt()
+}
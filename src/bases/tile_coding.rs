@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use super::function_approximation::{FeatureExtractor, StateVector};
+
+/// Index hash table of Sutton's tile coding software. Every new tile coordinate gets the next
+/// free index until `size` are in use, after that coordinates are hashed into the table and the
+/// collisions are counted in `overfull_count`.
+#[derive(Debug, Clone)]
+pub struct IndexHashTable {
+    pub size: usize,
+    pub overfull_count: usize,
+    dictionary: HashMap<Vec<i64>, usize>,
+}
+
+impl IndexHashTable {
+    pub fn new(size: usize) -> Self {
+        IndexHashTable {
+            size,
+            overfull_count: 0,
+            dictionary: HashMap::new(),
+        }
+    }
+
+    /// Indices handed out so far.
+    pub fn count(&self) -> usize {
+        self.dictionary.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.count() >= self.size
+    }
+
+    /// Index of the tile with coordinates `coordinates`. With `read_only` unseen tiles are not
+    /// added and give None.
+    pub fn index(&mut self, coordinates: &[i64], read_only: bool) -> Option<usize> {
+        if let Some(&index) = self.dictionary.get(coordinates) {
+            return Some(index);
+        }
+        if read_only {
+            return None;
+        }
+        if self.is_full() {
+            self.overfull_count += 1;
+            let mut hasher = DefaultHasher::new();
+            coordinates.hash(&mut hasher);
+            return Some(hasher.finish() as usize % self.size);
+        }
+        let index = self.count();
+        self.dictionary.insert(coordinates.to_vec(), index);
+        Some(index)
+    }
+}
+
+/// Active tile of each of the `num_tilings` tilings. Every float should be scaled so that one
+/// unit is the width of a tile, the tilings are offset by 1/num_tilings of a tile from each other
+/// with the asymmetric displacement (1, 3, 5, ...) across dimensions. `ints` are appended to the
+/// coordinates, for instance to give each action its own tiles.
+pub fn tiles(
+    iht: &mut IndexHashTable,
+    num_tilings: usize,
+    floats: &[f64],
+    ints: &[i64],
+) -> Vec<usize> {
+    tiles_wrap(iht, num_tilings, floats, &vec![None; floats.len()], ints)
+}
+
+/// `tiles` where the coordinates of the dimensions with a wrap width are taken modulo it, so
+/// that an angle scaled to `width` tiles per turn has its first and last tiles adjacent.
+pub fn tiles_wrap(
+    iht: &mut IndexHashTable,
+    num_tilings: usize,
+    floats: &[f64],
+    wrap_widths: &[Option<i64>],
+    ints: &[i64],
+) -> Vec<usize> {
+    let quantized: Vec<i64> = floats
+        .iter()
+        .map(|f| (f * num_tilings as f64).floor() as i64)
+        .collect();
+    (0..num_tilings as i64)
+        .filter_map(|tiling| {
+            let mut coordinates = Vec::with_capacity(1 + floats.len() + ints.len());
+            coordinates.push(tiling);
+            let mut b = tiling;
+            for (q, wrap) in quantized.iter().zip(wrap_widths) {
+                let c = (q + b).div_euclid(num_tilings as i64);
+                coordinates.push(match wrap {
+                    Some(width) => c.rem_euclid(*width),
+                    None => c,
+                });
+                b += tiling * 2;
+            }
+            coordinates.extend_from_slice(ints);
+            iht.index(&coordinates, false)
+        })
+        .collect()
+}
+
+/// Tile coding of the vector view of a state (Section 9.5.4). Each dimension is scaled so that
+/// `tiles_per_dimension` tiles cover its range [low, high], and tiles are numbered by an index
+/// hash table of `size` entries shared by all tilings.
+#[derive(Debug)]
+pub struct TileCoder<S> {
+    pub num_tilings: usize,
+    pub low: Vec<f64>,
+    pub high: Vec<f64>,
+    pub tiles_per_dimension: Vec<f64>,
+    /// Wrap width of each dimension in tiles, None where the dimension does not wrap.
+    pub wrap: Vec<Option<i64>>,
+    pub iht: RefCell<IndexHashTable>,
+    state: PhantomData<S>,
+}
+
+impl<S> TileCoder<S>
+where
+    S: StateVector,
+{
+    pub fn new(
+        num_tilings: usize,
+        low: Vec<f64>,
+        high: Vec<f64>,
+        tiles_per_dimension: Vec<f64>,
+        size: usize,
+    ) -> Self {
+        let wrap = vec![None; low.len()];
+        TileCoder {
+            num_tilings,
+            low,
+            high,
+            tiles_per_dimension,
+            wrap,
+            iht: RefCell::new(IndexHashTable::new(size)),
+            state: PhantomData,
+        }
+    }
+
+    /// Makes the dimension wrap around, its range then covers exactly its tiles.
+    pub fn with_wrap(mut self, dimension: usize) -> Self {
+        self.wrap[dimension] = Some(self.tiles_per_dimension[dimension].round() as i64);
+        self
+    }
+
+    /// Indices of the tiles active in `state`, one per tiling, extra `ints` give separate tiles.
+    pub fn active_tiles(&self, state: &S, ints: &[i64]) -> Vec<usize> {
+        let floats: Vec<f64> = state
+            .to_vector()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                (x - self.low[i]) * self.tiles_per_dimension[i] / (self.high[i] - self.low[i])
+            })
+            .collect();
+        tiles_wrap(
+            &mut self.iht.borrow_mut(),
+            self.num_tilings,
+            &floats,
+            &self.wrap,
+            ints,
+        )
+    }
+
+    /// Hash collisions so far.
+    pub fn collisions(&self) -> usize {
+        self.iht.borrow().overfull_count
+    }
+}
+
+impl<S> FeatureExtractor<S> for TileCoder<S>
+where
+    S: StateVector,
+{
+    /// Binary features with a one for every active tile.
+    fn features(&self, state: &S) -> Vec<f32> {
+        let mut x = vec![0.0; self.dimension()];
+        for index in self.active_tiles(state, &[]) {
+            x[index] = 1.0;
+        }
+        x
+    }
+    fn dimension(&self) -> usize {
+        self.iht.borrow().size
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use super::mdp::{Action, EnviormentModel, State};
+
+#[derive(Debug, Clone)]
+pub struct UpdateDistributionSettings {
+    pub gamma: f32,
+    /// Exploration of the ε-greedy policy that generates the sampled trajectories.
+    pub epsilon: f32,
+    /// Expected updates performed by each run.
+    pub updates: usize,
+    /// The start state value of the greedy policy is computed every this many updates.
+    pub eval_every: usize,
+    /// Stopping threshold of the evaluation of the greedy policy, in units of reward.
+    pub tolerance: f32,
+    /// Sweeps after which the evaluation stops anyway, a greedy policy that cycles without
+    /// discounting never converges.
+    pub max_sweeps: usize,
+}
+
+impl Default for UpdateDistributionSettings {
+    fn default() -> Self {
+        UpdateDistributionSettings {
+            gamma: 1.0,
+            epsilon: 0.1,
+            updates: 20_000,
+            eval_every: 500,
+            tolerance: 1e-3,
+            max_sweeps: 1000,
+        }
+    }
+}
+
+/// (next state, reward, probability) outcomes of one action, terminal outcomes have no next
+/// state.
+type Outcomes = Vec<(Option<usize>, f32, f32)>;
+
+/// The dynamics of a model copied into tables indexed by position in `states`, so that a run can
+/// make hundreds of thousands of expected updates without hashing.
+struct IndexedModel<A> {
+    actions: Vec<Vec<A>>,
+    outcomes: Vec<Vec<Outcomes>>,
+}
+
+impl<A> IndexedModel<A>
+where
+    A: Action,
+{
+    fn new<E, S>(env: &E, states: &[S]) -> Self
+    where
+        S: State,
+        E: EnviormentModel<S, A>,
+    {
+        let index: HashMap<&S, usize> = states.iter().enumerate().map(|(i, s)| (s, i)).collect();
+        let mut actions = Vec::with_capacity(states.len());
+        let mut outcomes = Vec::with_capacity(states.len());
+        for state in states {
+            let state_actions = env.posible_actions(state);
+            outcomes.push(
+                state_actions
+                    .iter()
+                    .map(|action| {
+                        env.dynamics(state, action)
+                            .into_iter()
+                            .map(|((next_state, reward), prob)| {
+                                let next = if env.is_terminal(&next_state) {
+                                    None
+                                } else {
+                                    Some(index[&next_state])
+                                };
+                                (next, reward as f32, prob)
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            );
+            actions.push(state_actions);
+        }
+        IndexedModel { actions, outcomes }
+    }
+
+    fn expected_update(&self, q: &[Vec<f32>], state: usize, action: usize, gamma: f32) -> f32 {
+        self.outcomes[state][action]
+            .iter()
+            .map(|&(next, reward, prob)| {
+                let next_value = next.map_or(0.0, |n| max_value(&q[n]));
+                prob * (reward + gamma * next_value)
+            })
+            .sum()
+    }
+
+    fn sample(&self, state: usize, action: usize) -> Option<usize> {
+        let outcomes = &self.outcomes[state][action];
+        let mut cutoff = thread_rng().gen_range(0.0..1.0);
+        for &(next, _, prob) in outcomes {
+            if cutoff < prob {
+                return next;
+            }
+            cutoff -= prob;
+        }
+        outcomes.last().and_then(|&(next, _, _)| next)
+    }
+
+    /// Value of `start` under the policy greedy with respect to `q`, computed by iterative
+    /// policy evaluation of at most `settings.max_sweeps` sweeps.
+    fn greedy_value(
+        &self,
+        q: &[Vec<f32>],
+        start: usize,
+        settings: &UpdateDistributionSettings,
+    ) -> f32 {
+        let gamma = settings.gamma;
+        let greedy: Vec<usize> = q.iter().map(|values| argmax(values)).collect();
+        let mut values = vec![0.0; q.len()];
+        for _ in 0..settings.max_sweeps {
+            let mut delta: f32 = 0.0;
+            for state in 0..values.len() {
+                let Some(outcomes) = self.outcomes[state].get(greedy[state]) else {
+                    continue;
+                };
+                let value: f32 = outcomes
+                    .iter()
+                    .map(|&(next, reward, prob)| {
+                        prob * (reward + gamma * next.map_or(0.0, |n| values[n]))
+                    })
+                    .sum();
+                delta = delta.max((value - values[state]).abs());
+                values[state] = value;
+            }
+            if delta < settings.tolerance {
+                break;
+            }
+        }
+        values[start]
+    }
+}
+
+/// Largest of the values, zero for states without actions.
+fn max_value(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+}
+
+fn argmax(values: &[f32]) -> usize {
+    (0..values.len())
+        .max_by(|&i, &j| values[i].total_cmp(&values[j]))
+        .unwrap_or(0)
+}
+
+fn zero_values<A>(model: &IndexedModel<A>) -> Vec<Vec<f32>> {
+    model
+        .actions
+        .iter()
+        .map(|actions| vec![0.0; actions.len()])
+        .collect()
+}
+
+/// Expected updates of the action values cycling in place through every state–action pair of
+/// the nonterminal `states` (Section 8.6). Returns the value of `start` under the greedy policy
+/// after every `settings.eval_every` updates, starting with the one before any update.
+pub fn uniform_updates<E, S, A>(
+    env: &E,
+    states: &[S],
+    start: &S,
+    settings: &UpdateDistributionSettings,
+) -> Vec<f32>
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    assert!(settings.eval_every > 0, "eval_every must be at least one");
+    let model = IndexedModel::new(env, states);
+    let start = states.iter().position(|s| s == start).unwrap();
+    let pairs: Vec<(usize, usize)> = (0..states.len())
+        .flat_map(|s| (0..model.actions[s].len()).map(move |a| (s, a)))
+        .collect();
+    let mut q = zero_values(&model);
+    let mut curve = vec![model.greedy_value(&q, start, settings)];
+    for (update, &(state, action)) in pairs.iter().cycle().take(settings.updates).enumerate() {
+        q[state][action] = model.expected_update(&q, state, action, settings.gamma);
+        if (update + 1) % settings.eval_every == 0 {
+            curve.push(model.greedy_value(&q, start, settings));
+        }
+    }
+    curve
+}
+
+/// Expected updates of the pairs visited by simulated episodes that start in `start` and follow
+/// the ε-greedy policy with respect to the current values (trajectory sampling, Section 8.6).
+/// Returns the same curve as `uniform_updates`.
+pub fn on_policy_updates<E, S, A>(
+    env: &E,
+    states: &[S],
+    start: &S,
+    settings: &UpdateDistributionSettings,
+) -> Vec<f32>
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+{
+    assert!(settings.eval_every > 0, "eval_every must be at least one");
+    let mut rng = thread_rng();
+    let model = IndexedModel::new(env, states);
+    let start = states.iter().position(|s| s == start).unwrap();
+    let mut q = zero_values(&model);
+    let mut curve = vec![model.greedy_value(&q, start, settings)];
+    let mut state = start;
+    for update in 0..settings.updates {
+        let action = if rng.gen::<f32>() < settings.epsilon {
+            rng.gen_range(0..q[state].len())
+        } else {
+            let best = max_value(&q[state]);
+            let ties: Vec<usize> = (0..q[state].len())
+                .filter(|&a| q[state][a] == best)
+                .collect();
+            *ties.choose(&mut rng).unwrap()
+        };
+        q[state][action] = model.expected_update(&q, state, action, settings.gamma);
+        state = model.sample(state, action).unwrap_or(start);
+        if (update + 1) % settings.eval_every == 0 {
+            curve.push(model.greedy_value(&q, start, settings));
+        }
+    }
+    curve
+}
+
+/// Averages `uniform_updates` and `on_policy_updates` over `tasks` environments produced by
+/// `generate`, which returns a task with its nonterminal states and start state.
+pub fn compare_update_distributions<E, S, A, F>(
+    tasks: usize,
+    mut generate: F,
+    settings: &UpdateDistributionSettings,
+) -> (Vec<f32>, Vec<f32>)
+where
+    S: State,
+    A: Action,
+    E: EnviormentModel<S, A>,
+    F: FnMut() -> (E, Vec<S>, S),
+{
+    assert!(settings.eval_every > 0, "eval_every must be at least one");
+    let points = settings.updates / settings.eval_every + 1;
+    let mut uniform = vec![0.0; points];
+    let mut on_policy = vec![0.0; points];
+    for _ in 0..tasks {
+        let (env, states, start) = generate();
+        let curves = [
+            (
+                &mut uniform,
+                uniform_updates(&env, &states, &start, settings),
+            ),
+            (
+                &mut on_policy,
+                on_policy_updates(&env, &states, &start, settings),
+            ),
+        ];
+        for (total, curve) in curves {
+            for (point, value) in total.iter_mut().zip(curve) {
+                *point += value / tasks as f32;
+            }
+        }
+    }
+    (uniform, on_policy)
+}
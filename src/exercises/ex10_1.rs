@@ -0,0 +1,259 @@
+/*
+Example 10.1: Mountain Car Task. Consider the task of driving an underpowered car up a steep
+mountain road. The difficulty is that gravity is stronger than the car's engine, and even at full
+throttle the car cannot accelerate up the steep slope. The only solution is to first move away from
+the goal and up the opposite slope on the left. Then, by applying full throttle the car can build up
+enough inertia to carry it up the steep slope even though it is slowing down the whole way.
+
+The reward in this problem is −1 on all time steps until the car moves past its goal position at
+the top of the mountain, which ends the episode. There are three possible actions: full throttle
+forward (+1), full throttle reverse (−1), and zero throttle (0). The car moves according to a
+simplified physics. Its position and velocity are updated by
+
+    x_{t+1} = bound[x_t + ẋ_{t+1}]
+    ẋ_{t+1} = bound[ẋ_t + 0.001 A_t − 0.0025 cos(3 x_t)]
+
+where the bound operation enforces −1.2 ≤ x_{t+1} ≤ 0.5 and −0.07 ≤ ẋ_{t+1} ≤ 0.07. In addition,
+when x_{t+1} reached the left bound, ẋ_{t+1} was reset to zero. When it reached the right bound, the
+goal was reached and the episode was terminated. Each episode started from a random position
+x_t ∈ [−0.6, −0.4) and zero velocity. To convert the two continuous state variables to binary
+features, we used grid-tilings: 8 tilings, each tile covering 1/8th of the bounded distance in each
+dimension, and asymmetric offsets.
+*/
+
+use std::hash::{Hash, Hasher};
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    bases::{
+        basis_functions::{Bounds, FourierBasis},
+        function_approximation::{FeatureExtractor, LinearActionValues, StateVector},
+        mdp::{Action, Enviorment, State},
+        neural_network::{Activation, Network, NetworkActionValues, Optimizer},
+        semi_gradient::{semi_gradient_n_step_sarsa, semi_gradient_sarsa},
+        td_control::TdParameters,
+        tile_coding::TileCoder,
+    },
+    utils::plot::plot_curves,
+};
+
+pub const POSITION_RANGE: (f64, f64) = (-1.2, 0.5);
+pub const VELOCITY_RANGE: (f64, f64) = (-0.07, 0.07);
+
+/// Continuous state, compared and hashed bit for bit so it can be used by the generic code.
+#[derive(Clone, Copy, Debug)]
+pub struct MountainCarState {
+    pub position: f64,
+    pub velocity: f64,
+}
+
+impl PartialEq for MountainCarState {
+    fn eq(&self, other: &Self) -> bool {
+        self.position.to_bits() == other.position.to_bits()
+            && self.velocity.to_bits() == other.velocity.to_bits()
+    }
+}
+
+impl Eq for MountainCarState {}
+
+impl Hash for MountainCarState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.position.to_bits().hash(state);
+        self.velocity.to_bits().hash(state);
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum Throttle {
+    Reverse,
+    Zero,
+    Forward,
+}
+
+impl State for MountainCarState {}
+impl Action for Throttle {}
+
+impl StateVector for MountainCarState {
+    fn to_vector(&self) -> Vec<f64> {
+        vec![self.position, self.velocity]
+    }
+}
+
+pub struct MountainCar;
+
+impl MountainCar {
+    /// `count` starting states with positions drawn uniformly from [−0.6, −0.4).
+    pub fn starting_states(&self, count: usize) -> Vec<MountainCarState> {
+        let mut rng = thread_rng();
+        (0..count)
+            .map(|_| MountainCarState {
+                position: rng.gen_range(-0.6..-0.4),
+                velocity: 0.0,
+            })
+            .collect()
+    }
+
+    /// The grid tilings of the example over position and velocity.
+    pub fn tile_coder(&self, num_tilings: usize) -> TileCoder<MountainCarState> {
+        TileCoder::new(
+            num_tilings,
+            vec![POSITION_RANGE.0, VELOCITY_RANGE.0],
+            vec![POSITION_RANGE.1, VELOCITY_RANGE.1],
+            vec![8.0, 8.0],
+            2048,
+        )
+    }
+}
+
+impl<'a> Enviorment<'a, MountainCarState, Throttle> for MountainCar {
+    fn response(&self, state: &MountainCarState, action: &Throttle) -> (MountainCarState, i32) {
+        if self.is_terminal(state) {
+            return (*state, 0);
+        }
+        let throttle = match action {
+            Throttle::Reverse => -1.0,
+            Throttle::Zero => 0.0,
+            Throttle::Forward => 1.0,
+        };
+        let mut velocity = (state.velocity + 0.001 * throttle
+            - 0.0025 * (3.0 * state.position).cos())
+        .clamp(VELOCITY_RANGE.0, VELOCITY_RANGE.1);
+        let position = (state.position + velocity).clamp(POSITION_RANGE.0, POSITION_RANGE.1);
+        if position == POSITION_RANGE.0 {
+            velocity = 0.0;
+        }
+        (MountainCarState { position, velocity }, -1)
+    }
+    fn is_terminal(&self, state: &MountainCarState) -> bool {
+        state.position >= POSITION_RANGE.1
+    }
+    fn posible_actions(&self, state: &MountainCarState) -> Vec<Throttle> {
+        let _ = state;
+        vec![Throttle::Reverse, Throttle::Zero, Throttle::Forward]
+    }
+    /// The state space is continuous, there is nothing to enumerate.
+    fn get_states(&self) -> Vec<MountainCarState> {
+        Vec::new()
+    }
+}
+
+/// Steps per episode of semi-gradient n-step Sarsa with 8 tilings averaged over runs, one curve
+/// per (n, α) setting with α divided among the tilings. Weights start at zero, which is optimistic and drives exploration, so
+/// ε is zero as in the book.
+pub fn mountain_car_curves(settings: &[(usize, f32)], episodes: u32, runs: u32) -> Vec<Vec<f64>> {
+    let env = MountainCar;
+    let num_tilings = 8;
+    settings
+        .iter()
+        .map(|&(n, alpha)| {
+            let params = TdParameters {
+                alpha: alpha / num_tilings as f32,
+                gamma: 1.0,
+                epsilon: 0.0,
+                episodes,
+                max_steps: 10_000,
+            };
+            let mut curve = vec![0.0; episodes as usize];
+            for _ in 0..runs {
+                let coder = env.tile_coder(num_tilings);
+                let result = semi_gradient_n_step_sarsa(
+                    &env,
+                    LinearActionValues::new(coder.dimension()),
+                    &coder,
+                    &env.starting_states(1000),
+                    &params,
+                    n,
+                );
+                for (point, length) in curve.iter_mut().zip(result.lengths) {
+                    *point += length as f64 / runs as f64;
+                }
+            }
+            curve
+        })
+        .collect()
+}
+
+/// Figure 10.2 (one-step Sarsa with three step sizes) and Figure 10.3 (one-step against 8-step
+/// Sarsa), steps per episode on a log scale.
+pub fn solution10_1() {
+    let episodes = 500;
+    let runs = 10;
+    let figures = [
+        (
+            "Mountain car, one-step Sarsa",
+            "mountain_car_alpha.png",
+            vec![(1, 0.1), (1, 0.2), (1, 0.5)],
+        ),
+        (
+            "Mountain car, n-step Sarsa",
+            "mountain_car_n_step.png",
+            vec![(1, 0.5), (8, 0.3)],
+        ),
+    ];
+    for (caption, file, settings) in figures {
+        let curves = mountain_car_curves(&settings, episodes, runs);
+        let labels: Vec<String> = settings
+            .iter()
+            .map(|(n, alpha)| format!("n={n} a={alpha}/8"))
+            .collect();
+        for (label, curve) in labels.iter().zip(&curves) {
+            let tail = &curve[curve.len() - 50..];
+            println!(
+                "{label}: first episode {:.0} steps, mean of the last 50 {:.1}",
+                curve[0],
+                tail.iter().sum::<f64>() / tail.len() as f64
+            );
+        }
+        let curves: Vec<(&str, Vec<f64>)> = labels
+            .iter()
+            .zip(curves)
+            .map(|(label, curve)| (label.as_str(), curve.into_iter().map(f64::log10).collect()))
+            .collect();
+        plot_curves(&curves, caption, file).unwrap();
+    }
+}
+
+/// One-step semi-gradient Sarsa with the action values given by a small network with one output
+/// per throttle, fed the order 3 Fourier features of the position and velocity. Without
+/// optimistic values it explores ε-greedily, and Adam takes the place of the step size. Learning
+/// online, with no replay of past experience or separate target network, it often reaches the
+/// goal in under 300 steps and then falls apart again, the instability of Section 11.3.
+pub fn network_solution10_1() {
+    let env = MountainCar;
+    let bounds = Bounds::new(
+        vec![POSITION_RANGE.0, VELOCITY_RANGE.0],
+        vec![POSITION_RANGE.1, VELOCITY_RANGE.1],
+    );
+    let features = FourierBasis::new(3, bounds);
+    let network = Network::new(
+        &[features.dimension(), 32, 32, 3],
+        Activation::Relu,
+        Activation::Identity,
+        Optimizer::adam(1e-4).with_clip_norm(10.0),
+        None,
+    );
+    let actions = env.posible_actions(&env.starting_states(1)[0]);
+    let params = TdParameters {
+        alpha: 1.0,
+        gamma: 1.0,
+        epsilon: 0.1,
+        episodes: 300,
+        max_steps: 10_000,
+    };
+    let result = semi_gradient_sarsa(
+        &env,
+        NetworkActionValues::new(network, actions),
+        &features,
+        &env.starting_states(1000),
+        &params,
+    );
+    for (i, chunk) in result.lengths.chunks(50).enumerate() {
+        println!(
+            "episodes {}-{}: mean of {:.1} steps",
+            i * 50,
+            i * 50 + chunk.len(),
+            chunk.iter().sum::<usize>() as f32 / chunk.len() as f32
+        );
+    }
+}
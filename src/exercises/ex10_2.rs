@@ -0,0 +1,192 @@
+/*
+Example 10.2: An Access-Control Queuing Task. This is a decision task involving access control to a
+set of k servers. Customers of four different priorities arrive at a single queue. If given access
+to a server, the customers pay a reward of 1, 2, 4, or 8 to the server, depending on their priority,
+with higher priority customers paying more. In each time step, the customer at the head of the
+queue is either accepted (assigned to one of the servers) or rejected (removed from the queue, with
+a reward of zero). In either case, on the next time step the next customer in the queue is
+considered. The queue never empties, and the priorities of the customers in the queue are equally
+randomly distributed. Of course a customer cannot be served if there is no free server; the
+customer is always rejected in this case. Each busy server becomes free with probability p = 0.06
+on each time step. Although we have just described them for definiteness, let us assume the
+statistics of arrivals and departures are unknown. The task is to decide on each step whether to
+accept or reject the next customer, on the basis of his priority and the number of free servers, so
+as to maximize long-term reward without discounting.
+
+Figure 10.5 shows the solution found by differential semi-gradient Sarsa with parameters α = 0.01,
+β = 0.01, and ε = 0.1, for k = 10 servers. The initial action values and R̄ were zero.
+*/
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+
+use crate::bases::{
+    average_reward::{
+        differential_sarsa, differential_semi_gradient_sarsa, AverageRewardParameters,
+    },
+    function_approximation::{
+        ActionValueFunction, FeatureExtractor, LinearActionValues, TabularFeatures,
+    },
+    mdp::{Action, Enviorment, State},
+    td_control::{action_value, greedy_policy},
+};
+
+pub const PRIORITIES: [i32; 4] = [1, 2, 4, 8];
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct QueueState {
+    pub free_servers: u32,
+    /// Payment of the customer at the head of the queue.
+    pub priority: i32,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum Admission {
+    Accept,
+    Reject,
+}
+
+impl State for QueueState {}
+impl Action for Admission {}
+
+pub struct AccessControl {
+    pub servers: u32,
+    pub free_probability: f64,
+}
+
+impl AccessControl {
+    pub fn start(&self) -> QueueState {
+        QueueState {
+            free_servers: self.servers,
+            priority: PRIORITIES[thread_rng().gen_range(0..PRIORITIES.len())],
+        }
+    }
+}
+
+/// A continuing task, no state is terminal.
+impl<'a> Enviorment<'a, QueueState, Admission> for AccessControl {
+    fn response(&self, state: &QueueState, action: &Admission) -> (QueueState, i32) {
+        let mut rng = thread_rng();
+        let mut free_servers = state.free_servers;
+        let mut reward = 0;
+        if *action == Admission::Accept && free_servers > 0 {
+            free_servers -= 1;
+            reward = state.priority;
+        }
+        let busy = self.servers - free_servers;
+        free_servers += (0..busy)
+            .filter(|_| rng.gen_bool(self.free_probability))
+            .count() as u32;
+        let next_state = QueueState {
+            free_servers,
+            priority: PRIORITIES[rng.gen_range(0..PRIORITIES.len())],
+        };
+        (next_state, reward)
+    }
+    fn is_terminal(&self, state: &QueueState) -> bool {
+        let _ = state;
+        false
+    }
+    /// Accepting is not possible without a free server.
+    fn posible_actions(&self, state: &QueueState) -> Vec<Admission> {
+        if state.free_servers == 0 {
+            vec![Admission::Reject]
+        } else {
+            vec![Admission::Accept, Admission::Reject]
+        }
+    }
+    fn get_states(&self) -> Vec<QueueState> {
+        (0..=self.servers)
+            .flat_map(|free_servers| {
+                PRIORITIES.map(|priority| QueueState {
+                    free_servers,
+                    priority,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Figure 10.5, the policy and the differential value of the best action learned by tabular
+/// differential Sarsa in two million steps, then the same run with the linear learner over
+/// one-hot features.
+pub fn solution10_2() {
+    let env = AccessControl {
+        servers: 10,
+        free_probability: 0.06,
+    };
+    let params = AverageRewardParameters {
+        alpha: 0.01,
+        beta: 0.01,
+        epsilon: 0.1,
+        steps: 2_000_000,
+    };
+    let result = differential_sarsa(&env, HashMap::new(), &env.start(), &params);
+    let policy = greedy_policy(&env, &result.action_values);
+    println!("average reward {:?}", result.average_reward);
+    for priority in PRIORITIES.iter().rev() {
+        let row: String = (1..=env.servers)
+            .map(|free_servers| {
+                let state = QueueState {
+                    free_servers,
+                    priority: *priority,
+                };
+                match policy.get(&state) {
+                    Some(Admission::Accept) => 'A',
+                    Some(Admission::Reject) => 'R',
+                    None => '?',
+                }
+            })
+            .collect();
+        println!(
+            "priority {priority}: {row} (1 to {} free servers)",
+            env.servers
+        );
+    }
+    for priority in PRIORITIES {
+        let values: Vec<String> = (0..=env.servers)
+            .map(|free_servers| {
+                let state = QueueState {
+                    free_servers,
+                    priority,
+                };
+                let best = env
+                    .posible_actions(&state)
+                    .iter()
+                    .map(|action| action_value(&result.action_values, &state, action))
+                    .fold(f32::NEG_INFINITY, f32::max);
+                format!("{best:.1}")
+            })
+            .collect();
+        println!(
+            "priority {priority} differential values: {}",
+            values.join(" ")
+        );
+    }
+
+    let states = env.get_states();
+    let features = TabularFeatures::new(&states);
+    let linear = differential_semi_gradient_sarsa(
+        &env,
+        LinearActionValues::new(features.dimension()),
+        &features,
+        &env.start(),
+        &params,
+    );
+    let agreement = states
+        .iter()
+        .filter(|state| state.free_servers > 0)
+        .filter(|state| {
+            let x = features.features(state);
+            let accept = linear.action_values.value(&x, &Admission::Accept)
+                >= linear.action_values.value(&x, &Admission::Reject);
+            (policy.get(state) == Some(&Admission::Accept)) == accept
+        })
+        .count();
+    println!(
+        "linear over one-hot features: average reward {:?}, same decision as the tabular policy in {agreement} of {} states",
+        linear.average_reward,
+        env.servers as usize * PRIORITIES.len()
+    );
+}
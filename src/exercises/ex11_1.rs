@@ -0,0 +1,272 @@
+/*
+Example 11.1: Baird's Counterexample. Consider the episodic seven-state, two-action MDP shown in
+Figure 11.1. The dashed action takes the system to one of the six upper states with equal
+probability, whereas the solid action takes the system to the seventh state. The behavior policy b
+selects the dashed and solid actions with probabilities 6/7 and 1/7, so that the next-state
+distribution under it is uniform (the same for all nonterminal states), which is also the starting
+distribution for each episode. The target policy π always takes the solid action, and so the
+on-policy distribution (for π) is concentrated in the seventh state. The reward is zero on all
+transitions. The discount rate is γ = 0.99.
+
+Consider estimating the state-value under the linear parameterization indicated by the expression
+shown in each state circle. For example, the estimated value of the leftmost state is 2w1 + w8,
+where the subscript corresponds to the component of the overall weight vector w ∈ R⁸; this
+corresponds to a feature vector for the first state being x(1) = (2, 0, 0, 0, 0, 0, 0, 1)ᵀ. The
+reward is zero on all transitions, so the true value function is v_π(s) = 0, for all s, which can
+be exactly approximated if w = 0. If we apply semi-gradient TD(0) to this problem, then the weights
+diverge to infinity, as shown in Figure 11.2 (left). The instability occurs for any positive step
+size, no matter how small. In fact, it even occurs if an expected update is done as in dynamic
+programming (DP), as shown in Figure 11.2 (right). Figures 11.5 and 11.6 show TDC and emphatic TD
+on the same problem, both of which are stable.
+*/
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    bases::{
+        function_approximation::{value_error, FeatureExtractor},
+        gradient_td::{
+            expected_off_policy_td, off_policy_linear_td, projected_bellman_error,
+            GradientTdParameters, GradientTdResult, OffPolicyMethod,
+        },
+        mdp::{Action, Enviorment, Policy, State},
+    },
+    utils::plot::plot_curves,
+};
+
+/// The six upper states are 0 to 5 and the lower one is 6.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct BairdState {
+    pub index: usize,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum BairdAction {
+    Dashed,
+    Solid,
+}
+
+impl State for BairdState {}
+impl Action for BairdAction {}
+
+const UPPER_STATES: usize = 6;
+const LOWER: BairdState = BairdState {
+    index: UPPER_STATES,
+};
+
+/// Baird's counterexample as a continuing task, its features are those of Figure 11.1.
+pub struct Baird;
+
+impl Baird {
+    /// The behavior takes the solid action with probability 1/7 and the target always does.
+    pub fn policy<'a>(
+        &self,
+        states: &'a [BairdState],
+        actions: &'a [BairdAction],
+        solid_probability: f32,
+    ) -> Policy<'a, BairdState, BairdAction> {
+        let mut map = HashMap::new();
+        for state in states {
+            let distribution = actions
+                .iter()
+                .map(|action| match action {
+                    BairdAction::Dashed => (action, 1.0 - solid_probability),
+                    BairdAction::Solid => (action, solid_probability),
+                })
+                .collect();
+            map.insert(state, distribution);
+        }
+        Policy::Stochastic(map)
+    }
+
+    /// The weights of Figure 11.2, w = (1, 1, 1, 1, 1, 1, 10, 1).
+    pub fn initial_weights(&self) -> Vec<f32> {
+        vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 10.0, 1.0]
+    }
+
+    /// Uniform, the stationary distribution of the behavior policy.
+    pub fn behavior_distribution(&self) -> Vec<f32> {
+        vec![1.0 / (UPPER_STATES + 1) as f32; UPPER_STATES + 1]
+    }
+}
+
+impl FeatureExtractor<BairdState> for Baird {
+    fn features(&self, state: &BairdState) -> Vec<f32> {
+        let mut x = vec![0.0; UPPER_STATES + 2];
+        if *state == LOWER {
+            x[UPPER_STATES] = 1.0;
+            x[UPPER_STATES + 1] = 2.0;
+        } else {
+            x[state.index] = 2.0;
+            x[UPPER_STATES + 1] = 1.0;
+        }
+        x
+    }
+    fn dimension(&self) -> usize {
+        UPPER_STATES + 2
+    }
+}
+
+impl<'a> Enviorment<'a, BairdState, BairdAction> for Baird {
+    fn response(&self, state: &BairdState, action: &BairdAction) -> (BairdState, i32) {
+        let _ = state;
+        match action {
+            BairdAction::Dashed => (
+                BairdState {
+                    index: thread_rng().gen_range(0..UPPER_STATES),
+                },
+                0,
+            ),
+            BairdAction::Solid => (LOWER, 0),
+        }
+    }
+    fn is_terminal(&self, state: &BairdState) -> bool {
+        let _ = state;
+        false
+    }
+    fn posible_actions(&self, state: &BairdState) -> Vec<BairdAction> {
+        let _ = state;
+        vec![BairdAction::Dashed, BairdAction::Solid]
+    }
+    fn get_states(&self) -> Vec<BairdState> {
+        (0..=UPPER_STATES)
+            .map(|index| BairdState { index })
+            .collect()
+    }
+}
+
+/// Known dynamics for the expected updates and the projected Bellman error.
+impl crate::bases::mdp::EnviormentModel<BairdState, BairdAction> for Baird {
+    fn dynamics(
+        &self,
+        state: &BairdState,
+        action: &BairdAction,
+    ) -> HashMap<(BairdState, i32), f32> {
+        let _ = state;
+        match action {
+            BairdAction::Dashed => (0..UPPER_STATES)
+                .map(|index| ((BairdState { index }, 0), 1.0 / UPPER_STATES as f32))
+                .collect(),
+            BairdAction::Solid => HashMap::from([((LOWER, 0), 1.0)]),
+        }
+    }
+    fn posible_actions(&self, state: &BairdState) -> Vec<BairdAction> {
+        <Self as Enviorment<BairdState, BairdAction>>::posible_actions(self, state)
+    }
+    fn get_states(&self) -> Vec<BairdState> {
+        <Self as Enviorment<BairdState, BairdAction>>::get_states(self)
+    }
+    fn response(&self, state: &BairdState, action: &BairdAction) -> (BairdState, i32) {
+        <Self as Enviorment<BairdState, BairdAction>>::response(self, state, action)
+    }
+    fn is_terminal(&self, state: &BairdState) -> bool {
+        <Self as Enviorment<BairdState, BairdAction>>::is_terminal(self, state)
+    }
+}
+
+/// Plots every component of the recorded weights.
+fn plot_weights(result: &GradientTdResult, caption: &str, file_path: &str) {
+    let names: Vec<String> = (1..=result.approximation.weights.len())
+        .map(|i| format!("w{i}"))
+        .collect();
+    let curves: Vec<(&str, Vec<f64>)> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let curve = result.history.iter().map(|w| w[i] as f64).collect();
+            (name.as_str(), curve)
+        })
+        .collect();
+    plot_curves(&curves, caption, file_path).unwrap();
+}
+
+/// Figures 11.2, 11.5 and 11.6, the weights learned by semi-gradient off-policy TD and DP, which
+/// diverge, and by TDC, GTD2 and emphatic TD, sampled and expected, with the final √VE and √PBE.
+/// Sampled emphatic TD has so much variance on this task that it is as unstable as semi-gradient
+/// TD, as remarked in Section 11.8, only its expected version converges.
+pub fn solution11_1() {
+    let env = Baird;
+    let states = env.get_states();
+    let actions = env.posible_actions(&LOWER);
+    let behavior = env.policy(&states, &actions, 1.0 / (UPPER_STATES + 1) as f32);
+    let target = env.policy(&states, &actions, 1.0);
+    let distribution = env.behavior_distribution();
+    let truth: HashMap<BairdState, f32> = states.iter().map(|s| (*s, 0.0)).collect();
+    let mu: HashMap<BairdState, f32> = states.iter().copied().zip(distribution.clone()).collect();
+
+    let experiments = [
+        (
+            "semi-gradient off-policy TD",
+            OffPolicyMethod::SemiGradient,
+            0.01,
+            false,
+        ),
+        (
+            "semi-gradient DP",
+            OffPolicyMethod::SemiGradient,
+            0.01,
+            true,
+        ),
+        ("TDC", OffPolicyMethod::Tdc, 0.005, false),
+        ("expected TDC", OffPolicyMethod::Tdc, 0.005, true),
+        ("GTD2", OffPolicyMethod::Gtd2, 0.005, false),
+        ("expected GTD2", OffPolicyMethod::Gtd2, 0.005, true),
+        ("emphatic TD", OffPolicyMethod::Emphatic, 0.03, false),
+        (
+            "expected emphatic TD",
+            OffPolicyMethod::Emphatic,
+            0.03,
+            true,
+        ),
+    ];
+    for (name, method, alpha, expected) in experiments {
+        let params = GradientTdParameters {
+            alpha,
+            ..Default::default()
+        };
+        let result = if expected {
+            expected_off_policy_td(
+                &env,
+                &target,
+                &env,
+                env.initial_weights(),
+                &states,
+                &distribution,
+                method,
+                &params,
+            )
+        } else {
+            off_policy_linear_td(
+                &env,
+                &behavior,
+                &target,
+                &env,
+                env.initial_weights(),
+                &LOWER,
+                method,
+                &params,
+            )
+        };
+        let weights = &result.approximation.weights;
+        println!(
+            "{name}: √VE {:?}, √PBE {:?}, w = {weights:?}",
+            value_error(&result.approximation, &env, &truth, Some(&mu)),
+            projected_bellman_error(
+                &env,
+                &target,
+                &env,
+                weights,
+                &states,
+                &distribution,
+                params.gamma
+            )
+        );
+        plot_weights(
+            &result,
+            &format!("{name} on Baird's counterexample"),
+            &format!("baird_{}.png", name.replace(' ', "_")),
+        );
+    }
+}
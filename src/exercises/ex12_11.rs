@@ -0,0 +1,275 @@
+/*
+Section 12.11: Stable Off-policy Methods with Traces. Not an example from the book, a small test
+problem for the off-policy λ methods of Sections 12.9 to 12.11. The agent walks down a corridor,
+on every state it can move forward with no reward or exit, which ends the episode with a reward of
+−1. Moving forward from the last state ends the episode with a reward of +1. The target policy
+rarely exits while the behavior policy exits often, so returns that follow the behavior are much
+worse than returns that follow the target. Q(λ) without any correction of its traces backs up
+those returns and its estimates of q_π are biased towards the behavior policy as λ approaches 1,
+while Tree-Backup(λ), Retrace(λ) and GQ(λ) converge to q_π for every λ.
+*/
+
+use std::collections::HashMap;
+
+use crate::bases::{
+    eligibility_traces::TraceParameters,
+    mdp::{Action, Enviorment, Policy, State},
+    off_policy_traces::{gq_lambda, off_policy_lambda, TraceCorrection},
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct CorridorState {
+    pub position: u32,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum CorridorAction {
+    Forward,
+    Exit,
+}
+
+impl State for CorridorState {}
+impl Action for CorridorAction {}
+
+/// Corridor with positions `0..length`, position `length` is the terminal state.
+pub struct Corridor {
+    pub length: u32,
+}
+
+impl Corridor {
+    pub fn start(&self) -> CorridorState {
+        CorridorState { position: 0 }
+    }
+
+    pub fn nonterminal_states(&self) -> Vec<CorridorState> {
+        (0..self.length)
+            .map(|position| CorridorState { position })
+            .collect()
+    }
+
+    /// Exits with probability `exit_probability` on every nonterminal state of `states`.
+    pub fn policy<'a>(
+        &self,
+        states: &'a [CorridorState],
+        actions: &'a [CorridorAction],
+        exit_probability: f32,
+    ) -> Policy<'a, CorridorState, CorridorAction> {
+        let mut map = HashMap::new();
+        for state in states {
+            let distribution = actions
+                .iter()
+                .map(|action| match action {
+                    CorridorAction::Forward => (action, 1.0 - exit_probability),
+                    CorridorAction::Exit => (action, exit_probability),
+                })
+                .collect();
+            map.insert(state, distribution);
+        }
+        Policy::Stochastic(map)
+    }
+
+    /// Undiscounted q_π of the policy that exits with probability `exit_probability`, computed
+    /// backwards from the end of the corridor.
+    pub fn true_action_values(
+        &self,
+        exit_probability: f32,
+    ) -> HashMap<(CorridorState, CorridorAction), f32> {
+        let mut values = HashMap::new();
+        let mut next_value = 0.0;
+        for position in (0..self.length).rev() {
+            let state = CorridorState { position };
+            let forward = if position + 1 == self.length {
+                1.0
+            } else {
+                next_value
+            };
+            values.insert((state, CorridorAction::Forward), forward);
+            values.insert((state, CorridorAction::Exit), -1.0);
+            next_value = (1.0 - exit_probability) * forward - exit_probability;
+        }
+        values
+    }
+
+    /// One-hot features over the state-action pairs.
+    pub fn features(&self, state: &CorridorState, action: &CorridorAction) -> Vec<f32> {
+        let mut x = vec![0.0; 2 * self.length as usize];
+        let offset = match action {
+            CorridorAction::Forward => 0,
+            CorridorAction::Exit => 1,
+        };
+        x[2 * state.position as usize + offset] = 1.0;
+        x
+    }
+}
+
+impl<'a> Enviorment<'a, CorridorState, CorridorAction> for Corridor {
+    fn response(&self, state: &CorridorState, action: &CorridorAction) -> (CorridorState, i32) {
+        let terminal = CorridorState {
+            position: self.length,
+        };
+        match action {
+            CorridorAction::Exit => (terminal, -1),
+            CorridorAction::Forward if state.position + 1 == self.length => (terminal, 1),
+            CorridorAction::Forward => (
+                CorridorState {
+                    position: state.position + 1,
+                },
+                0,
+            ),
+        }
+    }
+    fn is_terminal(&self, state: &CorridorState) -> bool {
+        state.position >= self.length
+    }
+    fn posible_actions(&self, state: &CorridorState) -> Vec<CorridorAction> {
+        let _ = state;
+        vec![CorridorAction::Forward, CorridorAction::Exit]
+    }
+    fn get_states(&self) -> Vec<CorridorState> {
+        (0..=self.length)
+            .map(|position| CorridorState { position })
+            .collect()
+    }
+}
+
+/// RMS error of `vals` over the pairs of `reference`, missing pairs count as zero.
+fn rms_error(
+    reference: &HashMap<(CorridorState, CorridorAction), f32>,
+    vals: &HashMap<(CorridorState, CorridorAction), f32>,
+) -> f32 {
+    let total: f32 = reference
+        .iter()
+        .map(|(pair, v)| (vals.get(pair).copied().unwrap_or(0.0) - v).powi(2))
+        .sum();
+    (total / reference.len() as f32).sqrt()
+}
+
+/// RMS error of the final estimates of q_π for every method and λ, averaged over independent
+/// runs. Naive Q(λ) should get worse as λ grows while the other methods stay close to zero.
+pub fn solution12_11() {
+    let env = Corridor { length: 6 };
+    let states = env.nonterminal_states();
+    let actions = env.posible_actions(&env.start());
+    let target_exit = 0.1;
+    let behavior = env.policy(&states, &actions, 0.3);
+    let target = env.policy(&states, &actions, target_exit);
+    let reference = env.true_action_values(target_exit);
+    let runs = 20;
+
+    let rms = |vals: &HashMap<(CorridorState, CorridorAction), f32>| rms_error(&reference, vals);
+    let methods = [
+        ("Q(lambda)", TraceCorrection::Naive),
+        ("importance sampling", TraceCorrection::ImportanceSampling),
+        ("Tree-Backup(lambda)", TraceCorrection::TreeBackup),
+        ("Retrace(lambda)", TraceCorrection::Retrace),
+    ];
+    for lambda in [0.0, 0.5, 0.9, 1.0] {
+        let params = TraceParameters {
+            alpha: 0.02,
+            lambda,
+            episodes: 3000,
+            ..Default::default()
+        };
+        for (name, correction) in methods {
+            let mut error = 0.0;
+            for _ in 0..runs {
+                let vals = off_policy_lambda(
+                    &env,
+                    &behavior,
+                    &target,
+                    HashMap::new(),
+                    &[env.start()],
+                    &params,
+                    correction,
+                );
+                error += rms(&vals) / runs as f32;
+            }
+            println!("{name}, lambda={lambda}: rms error {error:?}");
+        }
+
+        let mut error = 0.0;
+        for _ in 0..runs {
+            let weights = gq_lambda(
+                &env,
+                &behavior,
+                &target,
+                |s, a| env.features(s, a),
+                2 * env.length as usize,
+                &[env.start()],
+                &params,
+                0.005,
+            );
+            let vals = reference
+                .keys()
+                .map(|(s, a)| {
+                    let value = env
+                        .features(s, a)
+                        .into_iter()
+                        .zip(&weights)
+                        .map(|(x, w)| x * w);
+                    ((*s, *a), value.sum())
+                })
+                .collect();
+            error += rms(&vals) / runs as f32;
+        }
+        println!("GQ(lambda), lambda={lambda}: rms error {error:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The corrected methods end around 0.015 and naive Q(λ) at λ = 1 around 0.45.
+    const ERROR_BOUND: f32 = 0.1;
+
+    fn mean_error(correction: TraceCorrection, lambda: f32) -> f32 {
+        let env = Corridor { length: 6 };
+        let states = env.nonterminal_states();
+        let actions = env.posible_actions(&env.start());
+        let behavior = env.policy(&states, &actions, 0.3);
+        let target = env.policy(&states, &actions, 0.1);
+        let reference = env.true_action_values(0.1);
+        let params = TraceParameters {
+            alpha: 0.02,
+            lambda,
+            episodes: 3000,
+            ..Default::default()
+        };
+        let runs = 3;
+        (0..runs)
+            .map(|_| {
+                let vals = off_policy_lambda(
+                    &env,
+                    &behavior,
+                    &target,
+                    HashMap::new(),
+                    &[env.start()],
+                    &params,
+                    correction,
+                );
+                rms_error(&reference, &vals)
+            })
+            .sum::<f32>()
+            / runs as f32
+    }
+
+    #[test]
+    fn tree_backup_and_retrace_converge_to_q_pi() {
+        for lambda in [0.0, 0.5, 0.9, 1.0] {
+            for correction in [TraceCorrection::TreeBackup, TraceCorrection::Retrace] {
+                let error = mean_error(correction, lambda);
+                assert!(
+                    error < ERROR_BOUND,
+                    "{correction:?} at lambda={lambda}: rms error {error}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn naive_q_lambda_is_biased_towards_the_behavior() {
+        let error = mean_error(TraceCorrection::Naive, 1.0);
+        assert!(error > ERROR_BOUND, "rms error {error}");
+    }
+}
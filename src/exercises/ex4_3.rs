@@ -25,8 +25,11 @@ use std::collections::HashMap;
 
 use crate::{
     bases::{
+        mcts::{mcts_episode, Budget, Mcts, MctsSettings, RolloutPolicy},
         mdp::{Action, Agent, EnviormentModel, Policy, State},
         policy_iteration::{greedy_policy, policy_iteration, value_iteration},
+        prioritized_sweeping::{full_sweep_planner, PlanningSettings},
+        random_mdp::{compare_solvers, RandomMdp, RandomMdpParameters},
     },
     utils::stats::sample_from_hashmap_dist,
 };
@@ -48,6 +51,12 @@ pub struct Casino {
     probability_of_win: f32, //ph
 }
 
+impl Casino {
+    pub fn new(probability_of_win: f32) -> Self {
+        Casino { probability_of_win }
+    }
+}
+
 impl EnviormentModel<GamblerState, GamblerAction> for Casino {
     fn dynamics(
         &self,
@@ -108,6 +117,23 @@ impl EnviormentModel<GamblerState, GamblerAction> for Casino {
 impl State for GamblerState {}
 impl Action for GamblerAction {}
 
+/// Generative interface for the sample based planners, the calls are spelled out since both
+/// traits have methods with the same names.
+impl<'a> crate::bases::mdp::Enviorment<'a, GamblerState, GamblerAction> for Casino {
+    fn response(&self, state: &GamblerState, action: &GamblerAction) -> (GamblerState, i32) {
+        <Self as EnviormentModel<GamblerState, GamblerAction>>::response(self, state, action)
+    }
+    fn is_terminal(&self, state: &GamblerState) -> bool {
+        <Self as EnviormentModel<GamblerState, GamblerAction>>::is_terminal(self, state)
+    }
+    fn posible_actions(&self, state: &GamblerState) -> Vec<GamblerAction> {
+        <Self as EnviormentModel<GamblerState, GamblerAction>>::posible_actions(self, state)
+    }
+    fn get_states(&self) -> Vec<GamblerState> {
+        <Self as EnviormentModel<GamblerState, GamblerAction>>::get_states(self)
+    }
+}
+
 fn plot_graph(
     values: HashMap<GamblerState, f32>,
     file_path: &str,
@@ -241,3 +267,72 @@ pub fn solution() -> HashMap<GamblerState, f32> {
     plot_graph_act(policy, "act_graph_PI.png").unwrap();
     values
 }
+
+/// MCTS as a decision time agent, the fraction of games it wins from a few capitals against the
+/// winning probability v* given by value iteration.
+pub fn mcts_solution() {
+    let casino = Casino::new(0.4);
+    let states = casino.get_states();
+    let settings = PlanningSettings {
+        theta: 1e-6,
+        ..Default::default()
+    };
+    let optimal = full_sweep_planner(&casino, &states, HashMap::new(), &settings, None).values;
+    let games = 200;
+    let mut mcts = Mcts::new(
+        MctsSettings {
+            exploration: 0.5,
+            budget: Budget::Iterations(2000),
+            max_depth: 20,
+            rollout_depth: 20,
+            ..Default::default()
+        },
+        RolloutPolicy::Uniform,
+    );
+    for capital in [10, 25, 50, 75] {
+        let start = GamblerState { capital };
+        let mut wins = 0;
+        for _ in 0..games {
+            let (trajectory, _) = mcts_episode(&casino, &mut mcts, &start, 100);
+            if trajectory.last().is_some_and(|step| step.2 == 1) {
+                wins += 1;
+            }
+        }
+        println!(
+            "capital {capital}: MCTS wins {:?}, v* {:?}",
+            wins as f32 / games as f32,
+            optimal[&start]
+        );
+    }
+}
+
+/// Value iteration and policy iteration on seeded random MDPs of every structure, the largest
+/// difference between the values they find and how long each took.
+pub fn random_mdp_solution() {
+    let params = RandomMdpParameters {
+        states: 200,
+        seed: Some(7),
+        ..Default::default()
+    };
+    let continuing = RandomMdpParameters {
+        termination: 0.0,
+        ..params.clone()
+    };
+    let mdps = [
+        ("branching", RandomMdp::generate(&params), 1.0),
+        ("garnet", RandomMdp::garnet(&continuing), 0.9),
+        ("chain", RandomMdp::chain(&continuing, 0.2), 0.9),
+        ("tree", RandomMdp::tree(&params, 4), 1.0),
+    ];
+    for (name, mdp, gamma) in mdps {
+        let comparison = compare_solvers(&mdp, gamma, 1e-3);
+        println!(
+            "{name} ({} states): largest difference {:?}, greedy policies agree on {:.0}% of the states, value iteration {:?}, policy iteration {:?}",
+            mdp.transitions.len(),
+            comparison.max_difference / mdp.reward_scale,
+            100.0 * comparison.policy_agreement,
+            comparison.value_iteration_time,
+            comparison.policy_iteration_time
+        );
+    }
+}
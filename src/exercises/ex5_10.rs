@@ -25,9 +25,26 @@ use rand::thread_rng;
 use rand::seq::SliceRandom;
 
 use crate::bases::{
-    mdp::{Action, Enviorment, Policy, State},
+    eligibility_traces::{
+        sarsa_lambda, sarsa_lambda_sweep, true_online_sarsa_lambda, watkins_q_lambda, TraceKind,
+        TraceParameters,
+    },
+    evaluation::{evaluate_policy, EvaluationSettings},
+    function_approximation::{FeatureExtractor, Linear, StateVector, ValueFunction},
+    mcts::{mcts_episode, Budget, Mcts, MctsSettings, RolloutPolicy},
+    mdp::{Action, Enviorment, EnviormentModel, Policy, State},
+    model_learning::EmpiricalModel,
     monte_carlo_control::first_visit_monte_carlo_control,
+    off_policy_monte_carlo::off_policy_monte_carlo_control,
+    off_policy_n_step::{off_policy_n_step_sarsa, q_sigma, tree_backup},
+    policy_iteration::{greedy_policy, value_iteration},
+    rollout_planning::{planned_episode, DecisionPlanner, RolloutPlanner, SparseSampling},
+    semi_gradient::semi_gradient_td_zero,
+    td_control::{expected_sarsa, q_learning, sarsa, TdParameters},
+    temporal_difference::td_zero_prediction,
+    tile_coding::TileCoder,
 };
+use crate::utils::plot::plot_curves;
 
 use super::ex4_3::Casino;
 
@@ -156,6 +173,17 @@ pub struct CarState {
 impl Action for CarAction {}
 impl State for CarState {}
 
+impl StateVector for CarState {
+    fn to_vector(&self) -> Vec<f64> {
+        vec![
+            self.position.0 as f64,
+            self.position.1 as f64,
+            self.velocity.0 as f64,
+            self.velocity.1 as f64,
+        ]
+    }
+}
+
 impl<'a> Enviorment<'a, CarState, CarAction> for RaceTrack {
     fn response(&self, state: &CarState, action: &CarAction) -> (CarState, i32) {
         let mut rng = thread_rng();
@@ -220,16 +248,13 @@ impl<'a> Enviorment<'a, CarState, CarAction> for RaceTrack {
     }
 }
 
-pub fn solution5_10() {
-    let mut rng = thread_rng();
-    let env = get_race_track();
+pub fn handcrafted_policy<'a>(
+    states: &'a [CarState],
+    actions: &'a [CarAction],
+    epsilon: f32,
+) -> Policy<'a, CarState, CarAction> {
     let mut map = HashMap::new();
-    let episodes = 1000;
-    let epsilon = 0.2;
-    let gamma = 1.0;
-    let states = env.get_states();
-    let actions = env.posible_actions(&states[0]);
-    for state in &states {
+    for state in states {
         let max_action;
         if state.position.1 < 13 {
             max_action = CarAction {
@@ -245,7 +270,7 @@ pub fn solution5_10() {
             };
         }
         let mut choice_dist = HashMap::new();
-        for action in &actions {
+        for action in actions {
             let prob = if *action == max_action {
                 1.0 - epsilon + epsilon / actions.len() as f32
             } else {
@@ -255,8 +280,41 @@ pub fn solution5_10() {
         }
         map.insert(state, choice_dist);
     }
+    Policy::Stochastic(map)
+}
 
-    let init_pol = Policy::Stochastic(map);
+/// Deterministic policy that follows `greedy` and takes `default` on the states it never saw.
+pub fn complete_policy<'a>(
+    greedy: &HashMap<CarState, CarAction>,
+    states: &[CarState],
+    default: CarAction,
+) -> Policy<'a, CarState, CarAction> {
+    let mut map = HashMap::new();
+    for state in states {
+        map.insert(*state, *greedy.get(state).unwrap_or(&default));
+    }
+    Policy::Deterministic(map)
+}
+
+pub fn starting_states(env: &RaceTrack) -> Vec<CarState> {
+    env.starting_line
+        .iter()
+        .map(|&x| CarState {
+            velocity: (0, 0),
+            position: x,
+        })
+        .collect()
+}
+
+pub fn solution5_10() {
+    let mut rng = thread_rng();
+    let env = get_race_track();
+    let episodes = 1000;
+    let epsilon = 0.2;
+    let gamma = 1.0;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let init_pol = handcrafted_policy(&states, &actions, epsilon);
     let mut init_vals = HashMap::new();
 
     for state in &states {
@@ -264,14 +322,7 @@ pub fn solution5_10() {
             init_vals.insert((state.clone(), action.clone()), -500.0);
         }
     }
-    let init_states: Vec<CarState> = env
-        .starting_line
-        .iter()
-        .map(|&x| CarState {
-            velocity: (0, 0),
-            position: x,
-        })
-        .collect();
+    let init_states = starting_states(&env);
 
     first_visit_monte_carlo_control(
         init_pol,
@@ -281,5 +332,433 @@ pub fn solution5_10() {
         env,
         epsilon,
         gamma,
+    );
+}
+
+/// Off-policy Monte Carlo control with the hand crafted soft policy as behavior, the learned
+/// greedy policy is then driven from each starting position.
+pub fn off_policy_solution5_10() {
+    let env = get_race_track();
+    let episodes = 1000;
+    let epsilon = 0.2;
+    let gamma = 1.0;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let behavior = handcrafted_policy(&states, &actions, epsilon);
+    let mut init_vals = HashMap::new();
+    for state in &states {
+        for action in &actions {
+            init_vals.insert((*state, *action), -500.0);
+        }
+    }
+    let init_states = starting_states(&env);
+
+    let result =
+        off_policy_monte_carlo_control(&behavior, init_vals, &init_states, episodes, &env, gamma);
+    let tail = &result.lengths[result.lengths.len().saturating_sub(100)..];
+    println!(
+        "behavior episodes: mean length of the last 100 {:?}",
+        tail.iter().sum::<usize>() as f32 / tail.len().max(1) as f32
+    );
+    let target = complete_policy(&result.policy, &states, actions[4]);
+    for init_state in &init_states {
+        let (trajectory, finished) = env.episode_with_limit(init_state, &target, 200);
+        println!(
+            "from {:?}: {:?} steps, finished: {:?}",
+            init_state.position,
+            trajectory.len(),
+            finished
+        );
+    }
+}
+
+/// Sarsa, Q-learning and Expected Sarsa against first visit Monte Carlo control, the returns of
+/// every episode are plotted together.
+pub fn td_solution5_10() {
+    let env = get_race_track();
+    let episodes = 1000;
+    let epsilon = 0.1;
+    let gamma = 1.0;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let init_states = starting_states(&env);
+    let mut init_vals = HashMap::new();
+    for state in &states {
+        for action in &actions {
+            init_vals.insert((*state, *action), -500.0);
+        }
+    }
+    let params = TdParameters {
+        alpha: 0.5,
+        gamma,
+        epsilon,
+        episodes,
+        max_steps: 10_000,
+    };
+
+    let sarsa_result = sarsa(&env, init_vals.clone(), &init_states, &params, None);
+    let q_result = q_learning(&env, init_vals.clone(), &init_states, &params, None);
+    let expected_result = expected_sarsa(&env, init_vals.clone(), &init_states, &params, None);
+    let init_pol = handcrafted_policy(&states, &actions, epsilon);
+    let mc_curve = first_visit_monte_carlo_control(
+        init_pol,
+        init_vals,
+        init_states,
+        episodes,
+        get_race_track(),
+        epsilon,
+        gamma,
+    );
+
+    let curve = |returns: &[f32]| returns.iter().map(|&g| g as f64).collect::<Vec<f64>>();
+    plot_curves(
+        &[
+            ("Sarsa", curve(&sarsa_result.returns)),
+            ("Q-learning", curve(&q_result.returns)),
+            ("Expected Sarsa", curve(&expected_result.returns)),
+            ("Monte Carlo", mc_curve),
+        ],
+        "Racetrack returns",
+        "racetrack_td.png",
+    )
+    .unwrap();
+}
+
+/// Off-policy n-step Sarsa, Tree Backup and Q(σ) learning greedy policies from episodes of the
+/// hand crafted soft policy, each learned policy is then evaluated with the noise still on.
+pub fn n_step_off_policy_solution5_10() {
+    let env = get_race_track();
+    let n = 4;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let behavior = handcrafted_policy(&states, &actions, 0.5);
+    let init_states = starting_states(&env);
+    let mut init_vals = HashMap::new();
+    for state in &states {
+        for action in &actions {
+            init_vals.insert((*state, *action), -500.0);
+        }
+    }
+    let params = TdParameters {
+        alpha: 0.1,
+        gamma: 1.0,
+        epsilon: 0.0,
+        episodes: 1000,
+        max_steps: 10_000,
+    };
+    let settings = EvaluationSettings {
+        episodes: 400,
+        max_steps: 500,
+        threads: 4,
+        ..Default::default()
+    };
+
+    let results = [
+        (
+            "off-policy Sarsa",
+            off_policy_n_step_sarsa(&env, &behavior, init_vals.clone(), &init_states, n, &params),
+        ),
+        (
+            "Tree Backup",
+            tree_backup(&env, &behavior, init_vals.clone(), &init_states, n, &params),
+        ),
+        (
+            "Q(sigma)",
+            q_sigma(&env, &behavior, init_vals, &init_states, n, &params, |t| {
+                (t % 2) as f32
+            }),
+        ),
+    ];
+    for (name, result) in results {
+        let target = complete_policy(&result.policy, &states, actions[4]);
+        let evaluation = evaluate_policy(&env, &target, &init_states, &settings);
+        println!(
+            "{}: mean return {:?} +- {:?}, finished {:?}",
+            name, evaluation.mean_return, evaluation.standard_error, evaluation.success_rate
+        );
+    }
+}
+
+/// Sarsa(λ), Watkins's Q(λ) and true online Sarsa(λ) against first visit Monte Carlo control,
+/// followed by a sweep over λ of the mean return of Sarsa(λ).
+pub fn traces_solution5_10() {
+    let env = get_race_track();
+    let episodes = 1000;
+    let epsilon = 0.2;
+    let gamma = 1.0;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let init_states = starting_states(&env);
+    let mut init_vals = HashMap::new();
+    for state in &states {
+        for action in &actions {
+            init_vals.insert((*state, *action), -500.0);
+        }
+    }
+    let params = TraceParameters {
+        alpha: 0.2,
+        gamma,
+        lambda: 0.9,
+        epsilon: 0.1,
+        episodes,
+        max_steps: 10_000,
+        trace: TraceKind::Replacing,
+    };
+
+    let sarsa_result = sarsa_lambda(&env, init_vals.clone(), &init_states, &params);
+    let watkins_result = watkins_q_lambda(&env, init_vals.clone(), &init_states, &params);
+    let true_online_result =
+        true_online_sarsa_lambda(&env, init_vals.clone(), &init_states, &params);
+    let init_pol = handcrafted_policy(&states, &actions, epsilon);
+    let mc_curve = first_visit_monte_carlo_control(
+        init_pol,
+        init_vals.clone(),
+        init_states.clone(),
+        episodes,
+        get_race_track(),
+        epsilon,
+        gamma,
+    );
+
+    let curve = |returns: &[f32]| returns.iter().map(|&g| g as f64).collect::<Vec<f64>>();
+    plot_curves(
+        &[
+            ("Sarsa(lambda)", curve(&sarsa_result.returns)),
+            ("Watkins Q(lambda)", curve(&watkins_result.returns)),
+            (
+                "true online Sarsa(lambda)",
+                curve(&true_online_result.returns),
+            ),
+            ("Monte Carlo", mc_curve.clone()),
+        ],
+        "Racetrack returns",
+        "racetrack_traces.png",
     )
+    .unwrap();
+
+    let mc_mean = mc_curve.iter().sum::<f64>() / mc_curve.len() as f64;
+    println!("Monte Carlo: mean return {:?}", mc_mean);
+    let lambdas = [0.0, 0.5, 0.8, 0.9, 0.95, 1.0];
+    for (lambda, mean) in sarsa_lambda_sweep(&env, &init_vals, &init_states, &lambdas, 1, &params) {
+        println!("Sarsa(lambda={:?}): mean return {:?}", lambda, mean);
+    }
+}
+
+/// Certainty equivalence: a model is fitted to episodes of an exploratory policy, solved by value
+/// iteration and its greedy policy is compared with Q-learning trained on as many episodes.
+pub fn model_based_solution5_10() {
+    let mut rng = thread_rng();
+    let env = get_race_track();
+    let episodes = 1000;
+    let max_steps = 2000;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let behavior = handcrafted_policy(&states, &actions, 0.5);
+    let init_states = starting_states(&env);
+    let evaluation_settings = EvaluationSettings {
+        episodes: 400,
+        max_steps: 500,
+        threads: 4,
+        ..Default::default()
+    };
+
+    let mut trajectories = Vec::new();
+    for _ in 0..episodes {
+        let mut state = *init_states.choose(&mut rng).unwrap();
+        let mut trajectory = Vec::new();
+        while !env.is_terminal(&state) && trajectory.len() < max_steps {
+            let action = behavior.sample_action(&state);
+            let (next_state, reward) = env.response(&state, &action);
+            trajectory.push((state, action, reward));
+            state = next_state;
+        }
+        let terminal = env.is_terminal(&state);
+        trajectories.push((trajectory, state, terminal));
+    }
+
+    for smoothing in [0.0, 0.1] {
+        let mut model = EmpiricalModel::new(smoothing);
+        for (trajectory, final_state, terminal) in &trajectories {
+            model.add_episode(trajectory, final_state, *terminal);
+        }
+        let model_states = model.get_states();
+        let values = value_iteration(&model, &model_states, None, 1.0, 1e-3);
+        let greedy = greedy_policy(&model, &model_states, &values, 1.0);
+        let policy = complete_policy(&greedy, &states, actions[4]);
+        let evaluation = evaluate_policy(&env, &policy, &init_states, &evaluation_settings);
+        println!(
+            "model with smoothing {:?} ({} transitions, {} states): mean return {:?} +- {:?}, finished {:?}",
+            smoothing,
+            model.len(),
+            model_states.len(),
+            evaluation.mean_return,
+            evaluation.standard_error,
+            evaluation.success_rate
+        );
+    }
+
+    let mut init_vals = HashMap::new();
+    for state in &states {
+        for action in &actions {
+            init_vals.insert((*state, *action), -500.0);
+        }
+    }
+    let params = TdParameters {
+        alpha: 0.1,
+        gamma: 1.0,
+        epsilon: 0.1,
+        episodes,
+        max_steps,
+    };
+    let result = q_learning(&env, init_vals, &init_states, &params, None);
+    let policy = complete_policy(&result.policy, &states, actions[4]);
+    let evaluation = evaluate_policy(&env, &policy, &init_states, &evaluation_settings);
+    println!(
+        "Q-learning: mean return {:?} +- {:?}, finished {:?}",
+        evaluation.mean_return, evaluation.standard_error, evaluation.success_rate
+    );
+}
+
+/// MCTS driving the car with the handcrafted policy as rollout policy, against that policy alone.
+pub fn mcts_solution5_10() {
+    let mut rng = thread_rng();
+    let env = get_race_track();
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let rollout = handcrafted_policy(&states, &actions, 0.1);
+    let init_states = starting_states(&env);
+    let mut mcts = Mcts::new(
+        MctsSettings {
+            exploration: 5.0,
+            budget: Budget::Iterations(500),
+            max_depth: 30,
+            rollout_depth: 200,
+            ..Default::default()
+        },
+        RolloutPolicy::Policy(&rollout),
+    );
+    let episodes = 20;
+    let mut total = 0.0;
+    for _ in 0..episodes {
+        let start = init_states.choose(&mut rng).unwrap();
+        let (trajectory, _) = mcts_episode(&env, &mut mcts, start, 500);
+        total += trajectory.iter().map(|step| step.2 as f32).sum::<f32>();
+    }
+    let settings = EvaluationSettings {
+        episodes: 400,
+        max_steps: 500,
+        ..Default::default()
+    };
+    let evaluation = evaluate_policy(&env, &rollout, &init_states, &settings);
+    println!(
+        "MCTS: mean return {:?} ({} nodes), rollout policy: mean return {:?}",
+        total / episodes as f32,
+        mcts.table.len(),
+        evaluation.mean_return
+    );
+}
+
+/// One step of rollout improvement and sparse sampling on top of the weak handcrafted policy of
+/// `solution5_10`, with the rollouts each needs per decision.
+pub fn rollout_solution5_10() {
+    let env = get_race_track();
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let base = handcrafted_policy(&states, &actions, 0.2);
+    let init_states = starting_states(&env);
+    let settings = EvaluationSettings {
+        episodes: 400,
+        max_steps: 500,
+        ..Default::default()
+    };
+    let evaluation = evaluate_policy(&env, &base, &init_states, &settings);
+    println!("base policy: mean return {:?}", evaluation.mean_return);
+
+    let episodes = 20;
+    let mut rollout = RolloutPlanner::new(&base, 5, 100, 1.0);
+    let (mean, steps) = play_planner(&env, &mut rollout, &init_states, episodes);
+    println!(
+        "rollout planner: mean return {:?}, {:?} rollouts per decision",
+        mean,
+        rollout.rollouts as f32 / steps as f32
+    );
+
+    let mut sparse = SparseSampling::new(2, 2, 1.0).with_leaf_policy(&base, 50);
+    let (mean, steps) = play_planner(&env, &mut sparse, &init_states, episodes);
+    println!(
+        "sparse sampling: mean return {:?}, {:?} rollouts and {:?} samples per decision",
+        mean,
+        sparse.rollouts as f32 / steps as f32,
+        sparse.samples as f32 / steps as f32
+    );
+}
+
+/// Mean return of `episodes` episodes driven by `planner` and the decisions it took.
+fn play_planner<'a, P>(
+    env: &RaceTrack,
+    planner: &mut P,
+    init_states: &[CarState],
+    episodes: usize,
+) -> (f32, usize)
+where
+    P: DecisionPlanner<'a, CarState, CarAction>,
+{
+    let mut rng = thread_rng();
+    let mut total = 0.0;
+    let mut steps = 0;
+    for _ in 0..episodes {
+        let start = init_states.choose(&mut rng).unwrap();
+        let (trajectory, _) = planned_episode(env, planner, start, 500);
+        total += trajectory.iter().map(|step| step.2 as f32).sum::<f32>();
+        steps += trajectory.len();
+    }
+    (total / episodes as f32, steps)
+}
+
+/// Semi-gradient TD(0) with tile coding of position and velocity evaluating the hand crafted
+/// soft policy, against tabular TD(0) with the same episodes and the mean return of the policy.
+/// With a small hash table the tiles collide and the estimate degrades.
+pub fn tile_coding_solution5_10() {
+    let env = get_race_track();
+    let episodes = 2000;
+    let num_tilings = 8;
+    let states = env.get_states();
+    let actions = env.posible_actions(&states[0]);
+    let pol = handcrafted_policy(&states, &actions, 0.2);
+    let init_states = starting_states(&env);
+    let evaluation = evaluate_policy(&env, &pol, &init_states, &Default::default());
+    let tabular = td_zero_prediction(&pol, HashMap::new(), &init_states, episodes, &env, 0.1, 1.0);
+    let start_value = |value: &dyn Fn(&CarState) -> f32| {
+        init_states.iter().map(value).sum::<f32>() / init_states.len() as f32
+    };
+    println!(
+        "mean return {:?}, tabular TD(0) start value {:?}",
+        evaluation.mean_return,
+        start_value(&|s| tabular.get(s).copied().unwrap_or(0.0))
+    );
+    for size in [64, 256, 4096] {
+        let coder = TileCoder::new(
+            num_tilings,
+            vec![0.0, 0.0, 0.0, 0.0],
+            vec![17.0, 32.0, 6.0, 6.0],
+            vec![4.0, 8.0, 2.0, 2.0],
+            size,
+        );
+        let approximation = semi_gradient_td_zero(
+            &pol,
+            Linear::new(coder.dimension()),
+            &coder,
+            &init_states,
+            episodes,
+            &env,
+            0.1 / num_tilings as f32,
+            1.0,
+        );
+        println!(
+            "{size} tiles: start value {:?}, {} tiles in use, {} collisions",
+            start_value(&|s| approximation.value(&coder.features(s))),
+            coder.iht.borrow().count(),
+            coder.collisions()
+        );
+    }
 }
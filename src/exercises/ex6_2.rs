@@ -0,0 +1,314 @@
+/*
+Example 6.2: Random Walk. A Markov reward process with five nonterminal states A, B, C, D, E in a
+row. Every episode starts in the center state C and moves left or right by one state on each step
+with equal probability. Episodes terminate on the extreme left or the extreme right; when an episode
+terminates on the right a reward of +1 occurs, all other rewards are zero. Without discounting, the
+true value of each state is the probability of terminating on the right: 1/6, 2/6, 3/6, 4/6 and 5/6
+for A through E. The example compares TD(0) and constant-α MC learning these values, and Figure 6.2
+compares both methods under batch training on the same growing set of episodes.
+*/
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    bases::{
+        function_approximation::{value_error, Linear, TabularFeatures},
+        mdp::{Action, Enviorment, Policy, State},
+        monte_carlo_prediction::constant_alpha_monte_carlo_prediction,
+        semi_gradient::{gradient_monte_carlo, semi_gradient_n_step_td, semi_gradient_td_zero},
+        temporal_difference::{batch_monte_carlo, batch_td_zero, rms_error, td_zero_prediction},
+    },
+    utils::plot::plot_curves,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct WalkState {
+    pub position: u32,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct WalkAction;
+
+impl State for WalkState {}
+impl Action for WalkAction {}
+
+/// Walk over `size` nonterminal states numbered from 1, positions 0 and `size + 1` are terminal.
+pub struct RandomWalk {
+    pub size: u32,
+    pub left_reward: i32,
+    pub right_reward: i32,
+}
+
+impl RandomWalk {
+    pub fn start(&self) -> WalkState {
+        WalkState {
+            position: self.size.div_ceil(2),
+        }
+    }
+
+    pub fn nonterminal_states(&self) -> Vec<WalkState> {
+        (1..=self.size)
+            .map(|position| WalkState { position })
+            .collect()
+    }
+
+    /// The only policy there is, kept so the walk can be fed to the generic learners.
+    pub fn policy<'a>(&self) -> Policy<'a, WalkState, WalkAction> {
+        let map = self
+            .get_states()
+            .into_iter()
+            .map(|state| (state, WalkAction))
+            .collect();
+        Policy::Deterministic(map)
+    }
+
+    /// Undiscounted values, which grow linearly from the left to the right reward.
+    pub fn true_values(&self) -> HashMap<WalkState, f32> {
+        let span = (self.right_reward - self.left_reward) as f32;
+        self.nonterminal_states()
+            .into_iter()
+            .map(|state| {
+                let p_right = state.position as f32 / (self.size + 1) as f32;
+                (state, self.left_reward as f32 + span * p_right)
+            })
+            .collect()
+    }
+}
+
+impl<'a> Enviorment<'a, WalkState, WalkAction> for RandomWalk {
+    fn response(&self, state: &WalkState, action: &WalkAction) -> (WalkState, i32) {
+        let _ = action;
+        if self.is_terminal(state) {
+            return (*state, 0);
+        }
+        let mut rng = thread_rng();
+        let position = if rng.gen_bool(0.5) {
+            state.position + 1
+        } else {
+            state.position - 1
+        };
+        let reward = if position == 0 {
+            self.left_reward
+        } else if position == self.size + 1 {
+            self.right_reward
+        } else {
+            0
+        };
+        (WalkState { position }, reward)
+    }
+    fn is_terminal(&self, state: &WalkState) -> bool {
+        state.position == 0 || state.position == self.size + 1
+    }
+    fn posible_actions(&self, state: &WalkState) -> Vec<WalkAction> {
+        let _ = state;
+        vec![WalkAction]
+    }
+    fn get_states(&self) -> Vec<WalkState> {
+        (0..=self.size + 1)
+            .map(|position| WalkState { position })
+            .collect()
+    }
+}
+
+/// Known dynamics of the walk so reference values can be computed with the DP solvers.
+impl crate::bases::mdp::EnviormentModel<WalkState, WalkAction> for RandomWalk {
+    fn dynamics(&self, state: &WalkState, action: &WalkAction) -> HashMap<(WalkState, i32), f32> {
+        let _ = action;
+        let mut distribution = HashMap::new();
+        if <Self as Enviorment<WalkState, WalkAction>>::is_terminal(self, state) {
+            return distribution;
+        }
+        for position in [state.position - 1, state.position + 1] {
+            let reward = if position == 0 {
+                self.left_reward
+            } else if position == self.size + 1 {
+                self.right_reward
+            } else {
+                0
+            };
+            distribution.insert((WalkState { position }, reward), 0.5);
+        }
+        distribution
+    }
+    fn posible_actions(&self, state: &WalkState) -> Vec<WalkAction> {
+        <Self as Enviorment<WalkState, WalkAction>>::posible_actions(self, state)
+    }
+    fn get_states(&self) -> Vec<WalkState> {
+        <Self as Enviorment<WalkState, WalkAction>>::get_states(self)
+    }
+    fn response(&self, state: &WalkState, action: &WalkAction) -> (WalkState, i32) {
+        <Self as Enviorment<WalkState, WalkAction>>::response(self, state, action)
+    }
+    fn is_terminal(&self, state: &WalkState) -> bool {
+        <Self as Enviorment<WalkState, WalkAction>>::is_terminal(self, state)
+    }
+}
+
+fn initial_values(walk: &RandomWalk) -> HashMap<WalkState, f32> {
+    walk.nonterminal_states()
+        .into_iter()
+        .map(|state| (state, 0.5))
+        .collect()
+}
+
+/// Right graph of Example 6.2, RMS error of TD(0) and constant-α MC averaged over runs.
+pub fn solution6_2() {
+    let walk = RandomWalk {
+        size: 5,
+        left_reward: 0,
+        right_reward: 1,
+    };
+    let runs = 100;
+    let episodes = 100;
+    let pol = walk.policy();
+    let states = walk.nonterminal_states();
+    let truth = walk.true_values();
+    let init_states = [walk.start()];
+
+    let mut curves = Vec::new();
+    for (name, alpha, td) in [
+        ("TD a=0.05", 0.05, true),
+        ("TD a=0.1", 0.1, true),
+        ("TD a=0.15", 0.15, true),
+        ("MC a=0.01", 0.01, false),
+        ("MC a=0.02", 0.02, false),
+        ("MC a=0.03", 0.03, false),
+        ("MC a=0.04", 0.04, false),
+    ] {
+        let mut curve = vec![0.0; episodes + 1];
+        for _ in 0..runs {
+            let mut values = initial_values(&walk);
+            curve[0] += rms_error(&values, &truth, &states) as f64 / runs as f64;
+            for point in curve.iter_mut().skip(1) {
+                values = if td {
+                    td_zero_prediction(&pol, values, &init_states, 1, &walk, alpha, 1.0)
+                } else {
+                    constant_alpha_monte_carlo_prediction(
+                        &pol,
+                        values,
+                        &init_states,
+                        1,
+                        &walk,
+                        alpha,
+                        1.0,
+                    )
+                };
+                *point += rms_error(&values, &truth, &states) as f64 / runs as f64;
+            }
+        }
+        curves.push((name, curve));
+    }
+    plot_curves(&curves, "Random walk RMS error", "random_walk.png").unwrap();
+}
+
+/// Figure 6.2, batch TD(0) against batch MC on the same growing set of episodes.
+pub fn batch_solution6_2() {
+    let walk = RandomWalk {
+        size: 5,
+        left_reward: 0,
+        right_reward: 1,
+    };
+    let runs = 20;
+    let episodes = 100;
+    let alpha = 0.001;
+    let tolerance = 1e-4;
+    let pol = walk.policy();
+    let states = walk.nonterminal_states();
+    let truth = walk.true_values();
+
+    let mut td_curve = vec![0.0; episodes];
+    let mut mc_curve = vec![0.0; episodes];
+    for run in 0..runs {
+        println!("run: {:?}", run);
+        let mut trajectories = Vec::new();
+        for episode in 0..episodes {
+            trajectories.push(walk.episode(&walk.start(), &pol));
+            let td_values =
+                batch_td_zero(&trajectories, initial_values(&walk), alpha, 1.0, tolerance);
+            let mc_values =
+                batch_monte_carlo(&trajectories, initial_values(&walk), alpha, 1.0, tolerance);
+            td_curve[episode] += rms_error(&td_values, &truth, &states) as f64 / runs as f64;
+            mc_curve[episode] += rms_error(&mc_values, &truth, &states) as f64 / runs as f64;
+        }
+    }
+    plot_curves(
+        &[("batch TD", td_curve), ("batch MC", mc_curve)],
+        "Batch training",
+        "random_walk_batch.png",
+    )
+    .unwrap();
+}
+
+/// The tabular learners written as linear ones over one-hot features, the RMS error after
+/// `episodes` episodes averaged over runs should match the one of `solution6_2`.
+pub fn approximation_solution6_2() {
+    let walk = RandomWalk {
+        size: 5,
+        left_reward: 0,
+        right_reward: 1,
+    };
+    let runs = 100;
+    let episodes = 100;
+    let pol = walk.policy();
+    let states = walk.nonterminal_states();
+    let truth = walk.true_values();
+    let init_states = [walk.start()];
+    let features = TabularFeatures::new(&states);
+    let initial = || Linear {
+        weights: vec![0.5; states.len()],
+    };
+
+    let mut errors = [0.0; 4];
+    for _ in 0..runs {
+        let tabular = td_zero_prediction(
+            &pol,
+            initial_values(&walk),
+            &init_states,
+            episodes,
+            &walk,
+            0.1,
+            1.0,
+        );
+        let monte_carlo = gradient_monte_carlo(
+            &pol,
+            initial(),
+            &features,
+            &init_states,
+            episodes,
+            &walk,
+            0.02,
+            1.0,
+        );
+        let td = semi_gradient_td_zero(
+            &pol,
+            initial(),
+            &features,
+            &init_states,
+            episodes,
+            &walk,
+            0.1,
+            1.0,
+        );
+        let n_step = semi_gradient_n_step_td(
+            &pol,
+            initial(),
+            &features,
+            &init_states,
+            episodes,
+            &walk,
+            2,
+            0.1,
+            1.0,
+        );
+        errors[0] += rms_error(&tabular, &truth, &states) / runs as f32;
+        for (error, approximation) in errors[1..].iter_mut().zip([monte_carlo, td, n_step]) {
+            *error += value_error(&approximation, &features, &truth, None) / runs as f32;
+        }
+    }
+    println!(
+        "RMS error after {episodes} episodes: tabular TD(0) {:?}, gradient MC {:?}, semi-gradient TD(0) {:?}, 2-step semi-gradient TD {:?}",
+        errors[0], errors[1], errors[2], errors[3]
+    );
+}
@@ -0,0 +1,126 @@
+/*
+Example 6.7: Maximization Bias Example. The small MDP has two nonterminal states A and B. Episodes
+always start in A with a choice between two actions, left and right. The right action transitions
+immediately to the terminal state with a reward and return of zero. The left action transitions to
+B, also with a reward of zero, from which there are many possible actions all of which cause
+immediate termination with a reward drawn from a normal distribution with mean −0.1 and variance
+1.0. Thus, the expected return for any trajectory starting with left is −0.1, and thus taking left
+in state A is always a mistake. Nevertheless, Q-learning initially learns strongly to favor left
+(Figure 6.5), while Double Q-learning is essentially unaffected by maximization bias.
+
+Rewards here are integers, so the ones from B are +1 with probability 0.45 and −1 otherwise, which
+keeps the mean at −0.1 and a variance close to 1.
+*/
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    bases::{
+        double_learning::double_q_learning,
+        mdp::{Action, Enviorment, State},
+        td_control::{q_learning, ActionTracker, TdParameters},
+    },
+    utils::plot::plot_curves,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum BiasState {
+    A,
+    B,
+    Terminal,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum BiasAction {
+    Left,
+    Right,
+    /// One of the actions available in B.
+    Exit(u8),
+}
+
+impl State for BiasState {}
+impl Action for BiasAction {}
+
+pub struct MaximizationBias {
+    pub b_actions: u8,
+}
+
+impl<'a> Enviorment<'a, BiasState, BiasAction> for MaximizationBias {
+    fn response(&self, state: &BiasState, action: &BiasAction) -> (BiasState, i32) {
+        let mut rng = thread_rng();
+        match (state, action) {
+            (BiasState::A, BiasAction::Left) => (BiasState::B, 0),
+            (BiasState::B, _) => {
+                let reward = if rng.gen_bool(0.45) { 1 } else { -1 };
+                (BiasState::Terminal, reward)
+            }
+            _ => (BiasState::Terminal, 0),
+        }
+    }
+    fn is_terminal(&self, state: &BiasState) -> bool {
+        *state == BiasState::Terminal
+    }
+    fn posible_actions(&self, state: &BiasState) -> Vec<BiasAction> {
+        match state {
+            BiasState::B => (0..self.b_actions).map(BiasAction::Exit).collect(),
+            _ => vec![BiasAction::Left, BiasAction::Right],
+        }
+    }
+    fn get_states(&self) -> Vec<BiasState> {
+        vec![BiasState::A, BiasState::B, BiasState::Terminal]
+    }
+}
+
+/// Figure 6.5, percentage of left actions from A for Q-learning and Double Q-learning.
+pub fn solution6_7() {
+    let env = MaximizationBias { b_actions: 10 };
+    let runs = 1000;
+    let params = TdParameters {
+        alpha: 0.1,
+        gamma: 1.0,
+        epsilon: 0.1,
+        episodes: 300,
+        max_steps: 10,
+    };
+    let init_states = [BiasState::A];
+
+    let mut q_curve = vec![0.0; params.episodes as usize];
+    let mut double_curve = vec![0.0; params.episodes as usize];
+    for _ in 0..runs {
+        let mut tracker = ActionTracker::new(BiasState::A, BiasAction::Left);
+        q_learning(
+            &env,
+            HashMap::new(),
+            &init_states,
+            &params,
+            Some(&mut tracker),
+        );
+        for (point, fraction) in q_curve.iter_mut().zip(&tracker.fractions) {
+            *point += 100.0 * *fraction as f64 / runs as f64;
+        }
+
+        let mut tracker = ActionTracker::new(BiasState::A, BiasAction::Left);
+        double_q_learning(
+            &env,
+            HashMap::new(),
+            &init_states,
+            &params,
+            Some(&mut tracker),
+        );
+        for (point, fraction) in double_curve.iter_mut().zip(&tracker.fractions) {
+            *point += 100.0 * *fraction as f64 / runs as f64;
+        }
+    }
+    plot_curves(
+        &[
+            ("Q-learning", q_curve),
+            ("Double Q-learning", double_curve),
+            ("optimal", vec![5.0; params.episodes as usize]),
+        ],
+        "% left actions from A",
+        "maximization_bias.png",
+    )
+    .unwrap();
+}
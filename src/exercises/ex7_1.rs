@@ -0,0 +1,116 @@
+/*
+Example 7.1: n-step TD Methods on the Random Walk. Consider using n-step TD methods on the random
+walk task of Example 6.2, but with 19 states instead of 5 and with a −1 outcome on the left (all
+values initialized to 0). Figure 7.2 shows the performance of n-step TD methods as a function of α,
+for various values of n. The performance measure for each parameter setting is the square-root of
+the average squared error between the predictions at the end of the episode for the 19 states and
+their true values, then averaged over the first 10 episodes and 100 repetitions of the whole
+experiment. Methods with an intermediate value of n worked best.
+*/
+
+use std::collections::HashMap;
+
+use crate::{
+    bases::{
+        eligibility_traces::{lambda_sweep, LambdaMethod, TraceKind, TraceParameters},
+        n_step::n_step_sweep,
+        policy_iteration::value_iteration,
+    },
+    utils::plot::plot_curves,
+};
+
+use super::ex6_2::{RandomWalk, WalkState};
+
+fn reference_values(walk: &RandomWalk) -> HashMap<WalkState, f32> {
+    let states = walk.nonterminal_states();
+    let values = value_iteration(walk, &states, None, 1.0, 1e-6);
+    states.iter().map(|state| (*state, values[state])).collect()
+}
+
+/// Figure 7.2, one curve per n with the RMS error over α in steps of 0.1 on the x axis.
+pub fn solution7_1() {
+    let walk = RandomWalk {
+        size: 19,
+        left_reward: -1,
+        right_reward: 1,
+    };
+    let reference = reference_values(&walk);
+    let ns = [1, 2, 4, 8, 16, 32, 64, 128, 256, 512];
+    let alphas: Vec<f32> = (0..=10).map(|i| i as f32 / 10.0).collect();
+    let points = n_step_sweep(
+        &walk,
+        &walk.policy(),
+        &[walk.start()],
+        &reference,
+        &ns,
+        &alphas,
+        10,
+        100,
+        1.0,
+    );
+
+    let labels: Vec<String> = ns.iter().map(|n| format!("n={n}")).collect();
+    let curves: Vec<(&str, Vec<f64>)> = ns
+        .iter()
+        .zip(&labels)
+        .map(|(n, label)| {
+            let curve = points
+                .iter()
+                .filter(|point| point.n == *n)
+                .map(|point| (point.rms as f64).min(0.55))
+                .collect();
+            (label.as_str(), curve)
+        })
+        .collect();
+    plot_curves(&curves, "n-step TD on the 19 state walk", "n_step_td.png").unwrap();
+}
+
+/// Figures 12.6 and 12.8 on the same walk, TD(λ) with accumulating traces and true online TD(λ),
+/// one curve per λ with the RMS error over α in steps of 0.1 on the x axis.
+pub fn td_lambda_solution7_1() {
+    let walk = RandomWalk {
+        size: 19,
+        left_reward: -1,
+        right_reward: 1,
+    };
+    let reference = reference_values(&walk);
+    let lambdas = [0.0, 0.4, 0.8, 0.9, 0.95, 0.975, 0.99, 1.0];
+    let alphas: Vec<f32> = (0..=10).map(|i| i as f32 / 10.0).collect();
+    let params = TraceParameters {
+        episodes: 10,
+        ..Default::default()
+    };
+    let labels: Vec<String> = lambdas.iter().map(|l| format!("lambda={l}")).collect();
+    for (method, file_path) in [
+        (
+            LambdaMethod::TdLambda(TraceKind::Accumulating),
+            "td_lambda.png",
+        ),
+        (LambdaMethod::TrueOnline, "true_online_td_lambda.png"),
+    ] {
+        let points = lambda_sweep(
+            &walk,
+            &walk.policy(),
+            &[walk.start()],
+            &reference,
+            &lambdas,
+            &alphas,
+            100,
+            &params,
+            method,
+        );
+        let curves: Vec<(&str, Vec<f64>)> = lambdas
+            .iter()
+            .zip(&labels)
+            .map(|(lambda, label)| {
+                let curve = points
+                    .iter()
+                    .filter(|(l, _, _)| l == lambda)
+                    .map(|(_, _, rms)| (*rms as f64).min(0.55))
+                    .collect();
+                (label.as_str(), curve)
+            })
+            .collect();
+        plot_curves(&curves, "TD(lambda) on the 19 state walk", file_path).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+/*
+Example 8.2: Blocking Maze. The maze is a 9 by 6 grid with a barrier across it, the agent starts at
+the bottom and the goal is in the top right corner. Initially, there is a short path from start to
+goal, to the right of the barrier. After 1000 time steps, the short path is "blocked," and a longer
+path is opened up along the left-hand side of the barrier. Reaching the goal gives a reward of +1,
+every other transition gives zero, and after reaching it the agent starts over. Figure 8.4 shows
+the average cumulative reward of Dyna-Q and Dyna-Q+: both find the new path after the change, but
+Dyna-Q+ does so faster because its exploration bonus keeps drawing it to parts of the maze it has
+not tried in a while.
+*/
+
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use crate::{
+    bases::{
+        dyna::{dyna_q, dyna_q_plus, DynaParameters},
+        mdp::{Action, Enviorment, State},
+    },
+    utils::plot::plot_curves,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct MazeState {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum MazeAction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl State for MazeState {}
+impl Action for MazeAction {}
+
+/// Grid maze whose walls change from `walls` to `switched_walls` once `switch_step` steps have
+/// been taken. The step count lives in a `Cell` since `Enviorment::response` takes `&self`.
+pub struct Maze {
+    pub width: i32,
+    pub height: i32,
+    pub start: MazeState,
+    pub goal: MazeState,
+    pub walls: Vec<MazeState>,
+    pub switched_walls: Vec<MazeState>,
+    pub switch_step: usize,
+    pub steps: Cell<usize>,
+}
+
+impl Maze {
+    /// Row of walls at height `y` covering the columns in `xs`.
+    pub fn wall_row(y: i32, xs: std::ops::RangeInclusive<i32>) -> Vec<MazeState> {
+        xs.map(|x| MazeState { x, y }).collect()
+    }
+
+    pub fn current_walls(&self) -> &[MazeState] {
+        if self.steps.get() < self.switch_step {
+            &self.walls
+        } else {
+            &self.switched_walls
+        }
+    }
+
+    /// Restores the initial layout, needed before every independent run.
+    pub fn reset(&self) {
+        self.steps.set(0);
+    }
+
+    /// Every cell that is not currently a wall.
+    pub fn open_states(&self) -> Vec<MazeState> {
+        let walls = self.current_walls();
+        let mut states = Vec::new();
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let state = MazeState { x, y };
+                if !walls.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+
+    /// Outcome of a move with the current walls, without counting it as a step.
+    fn step(&self, state: &MazeState, action: &MazeAction) -> (MazeState, i32) {
+        let (dx, dy) = match action {
+            MazeAction::Up => (0, 1),
+            MazeAction::Down => (0, -1),
+            MazeAction::Left => (-1, 0),
+            MazeAction::Right => (1, 0),
+        };
+        let mut next = MazeState {
+            x: (state.x + dx).clamp(0, self.width - 1),
+            y: (state.y + dy).clamp(0, self.height - 1),
+        };
+        if self.current_walls().contains(&next) {
+            next = *state;
+        }
+        let reward = if next == self.goal { 1 } else { 0 };
+        (next, reward)
+    }
+}
+
+impl<'a> Enviorment<'a, MazeState, MazeAction> for Maze {
+    fn response(&self, state: &MazeState, action: &MazeAction) -> (MazeState, i32) {
+        self.steps.set(self.steps.get() + 1);
+        self.step(state, action)
+    }
+    fn is_terminal(&self, state: &MazeState) -> bool {
+        *state == self.goal
+    }
+    fn posible_actions(&self, state: &MazeState) -> Vec<MazeAction> {
+        let _ = state;
+        vec![
+            MazeAction::Up,
+            MazeAction::Down,
+            MazeAction::Left,
+            MazeAction::Right,
+        ]
+    }
+    fn get_states(&self) -> Vec<MazeState> {
+        let mut states = Vec::new();
+        for x in 0..self.width {
+            for y in 0..self.height {
+                states.push(MazeState { x, y });
+            }
+        }
+        states
+    }
+}
+
+/// Known dynamics with the current walls, so the mazes can also be solved by planning alone.
+impl crate::bases::mdp::EnviormentModel<MazeState, MazeAction> for Maze {
+    fn dynamics(&self, state: &MazeState, action: &MazeAction) -> HashMap<(MazeState, i32), f32> {
+        let mut distribution = HashMap::new();
+        if *state != self.goal {
+            distribution.insert(self.step(state, action), 1.0);
+        }
+        distribution
+    }
+    fn posible_actions(&self, state: &MazeState) -> Vec<MazeAction> {
+        <Self as Enviorment<MazeState, MazeAction>>::posible_actions(self, state)
+    }
+    fn get_states(&self) -> Vec<MazeState> {
+        <Self as Enviorment<MazeState, MazeAction>>::get_states(self)
+    }
+    /// Planning queries do not count as steps, so they never move the wall switch forward.
+    fn response(&self, state: &MazeState, action: &MazeAction) -> (MazeState, i32) {
+        self.step(state, action)
+    }
+    fn is_terminal(&self, state: &MazeState) -> bool {
+        *state == self.goal
+    }
+}
+
+pub fn blocking_maze() -> Maze {
+    Maze {
+        width: 9,
+        height: 6,
+        start: MazeState { x: 3, y: 0 },
+        goal: MazeState { x: 8, y: 5 },
+        walls: Maze::wall_row(2, 0..=7),
+        switched_walls: Maze::wall_row(2, 1..=8),
+        switch_step: 1000,
+        steps: Cell::new(0),
+    }
+}
+
+/// Average cumulative reward of Dyna-Q and Dyna-Q+ over `runs` runs of `params.steps` steps,
+/// plotted to `file_path`.
+pub fn compare_dyna(
+    maze: &Maze,
+    params: &DynaParameters,
+    runs: u32,
+    caption: &str,
+    file_path: &str,
+) {
+    let plus_params = DynaParameters {
+        kappa: 1e-3,
+        ..params.clone()
+    };
+    let mut dyna_curve = vec![0.0; params.steps];
+    let mut plus_curve = vec![0.0; params.steps];
+    for _ in 0..runs {
+        maze.reset();
+        let result = dyna_q(maze, Default::default(), &[maze.start], params);
+        for (point, reward) in dyna_curve.iter_mut().zip(&result.cumulative_rewards) {
+            *point += *reward as f64 / runs as f64;
+        }
+        maze.reset();
+        let result = dyna_q_plus(maze, Default::default(), &[maze.start], &plus_params);
+        for (point, reward) in plus_curve.iter_mut().zip(&result.cumulative_rewards) {
+            *point += *reward as f64 / runs as f64;
+        }
+    }
+    plot_curves(
+        &[("Dyna-Q", dyna_curve), ("Dyna-Q+", plus_curve)],
+        caption,
+        file_path,
+    )
+    .unwrap();
+}
+
+/// Figure 8.4.
+pub fn solution8_2() {
+    let params = DynaParameters {
+        alpha: 1.0,
+        planning_steps: 10,
+        ..Default::default()
+    };
+    compare_dyna(
+        &blocking_maze(),
+        &params,
+        20,
+        "Cumulative reward on the blocking maze",
+        "blocking_maze.png",
+    );
+}
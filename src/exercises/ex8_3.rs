@@ -0,0 +1,46 @@
+/*
+Example 8.3: Shortcut Maze. The problem caused by this kind of environmental change is illustrated
+by the maze example shown in Figure 8.5. Initially, the optimal path is to go around the left side
+of the barrier (upper left). After 3000 steps, however, a shorter path is opened up along the right
+side, without disturbing the longer path. The regular Dyna-Q agent never switched to the shortcut.
+In fact, it never realized that it existed. Its model said that there was no shortcut, so the more
+it planned, the less likely it was to step to the right and discover it. Even with an ε-greedy
+policy, it is very unlikely that an agent will take so many exploratory actions as to discover the
+shortcut. The Dyna-Q+ agent, with its exploration bonus, does find it.
+*/
+
+use std::cell::Cell;
+
+use crate::bases::dyna::DynaParameters;
+
+use super::ex8_2::{compare_dyna, Maze, MazeState};
+
+pub fn shortcut_maze() -> Maze {
+    Maze {
+        width: 9,
+        height: 6,
+        start: MazeState { x: 3, y: 0 },
+        goal: MazeState { x: 8, y: 5 },
+        walls: Maze::wall_row(2, 1..=8),
+        switched_walls: Maze::wall_row(2, 1..=7),
+        switch_step: 3000,
+        steps: Cell::new(0),
+    }
+}
+
+/// Figure 8.5.
+pub fn solution8_3() {
+    let params = DynaParameters {
+        alpha: 1.0,
+        planning_steps: 50,
+        steps: 6000,
+        ..Default::default()
+    };
+    compare_dyna(
+        &shortcut_maze(),
+        &params,
+        10,
+        "Cumulative reward on the shortcut maze",
+        "shortcut_maze.png",
+    );
+}
@@ -0,0 +1,166 @@
+/*
+Example 8.4: Prioritized Sweeping on Mazes. Prioritized sweeping has been found to dramatically
+increase the speed at which optimal solutions are found in maze tasks, often by a factor of 5 to
+10. The mazes are the Dyna maze of Example 8.1 (a 9 by 6 grid with the start on the left, the goal
+in the top right corner and three short barriers) scaled up by splitting every cell into a square
+of cells, reward +1 on reaching the goal and γ = 0.95.
+
+Here prioritized sweeping is also used as a planner on the known model and compared with the full
+sweeps of value iteration by the number of backups each needs to get within a given error of v*, on
+the mazes and on the gambler's problem of Example 4.3.
+*/
+
+use std::cell::Cell;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use crate::bases::{
+    dyna::{dyna_q, DynaParameters},
+    function_approximation::{FeatureExtractor, TabularFeatures},
+    least_squares::{lspi, LeastSquaresSettings},
+    mdp::{EnviormentModel, Transition},
+    prioritized_sweeping::{
+        compare_backups, full_sweep_planner, prioritized_sweeping, PlanningSettings,
+    },
+    semi_gradient::approximate_greedy_action,
+};
+
+use super::{
+    ex4_3::Casino,
+    ex8_2::{Maze, MazeAction, MazeState},
+};
+
+/// The Dyna maze of Example 8.1 with every cell split into `scale` by `scale` cells.
+pub fn dyna_maze(scale: i32) -> Maze {
+    let cells = [(2, 2), (2, 3), (2, 4), (5, 1), (7, 3), (7, 4), (7, 5)];
+    let mut walls = Vec::new();
+    for (x, y) in cells {
+        for dx in 0..scale {
+            for dy in 0..scale {
+                walls.push(MazeState {
+                    x: x * scale + dx,
+                    y: y * scale + dy,
+                });
+            }
+        }
+    }
+    Maze {
+        width: 9 * scale,
+        height: 6 * scale,
+        start: MazeState { x: 0, y: 3 * scale },
+        goal: MazeState {
+            x: 9 * scale - 1,
+            y: 6 * scale - 1,
+        },
+        walls: walls.clone(),
+        switched_walls: walls,
+        switch_step: usize::MAX,
+        steps: Cell::new(0),
+    }
+}
+
+/// Backups of full sweeps and prioritized sweeping to get within 1e-3 of v*, then the episode
+/// lengths reached online by Dyna-Q and prioritized sweeping with the same number of planning
+/// updates per step.
+pub fn solution8_4() {
+    let target_error = 1e-3;
+    let settings = PlanningSettings {
+        theta: 1e-7,
+        ..Default::default()
+    };
+    let casino = Casino::new(0.4);
+    let states = casino.get_states();
+    let reference = full_sweep_planner(&casino, &states, Default::default(), &settings, None);
+    let (sweeps, prioritized) =
+        compare_backups(&casino, &states, &reference.values, target_error, &settings);
+    println!("gambler: value iteration {sweeps} backups, prioritized sweeping {prioritized}");
+
+    let settings = PlanningSettings {
+        gamma: 0.95,
+        ..settings
+    };
+    for scale in 1..=4 {
+        let maze = dyna_maze(scale);
+        let states = maze.open_states();
+        let reference = full_sweep_planner(&maze, &states, Default::default(), &settings, None);
+        let (sweeps, prioritized) =
+            compare_backups(&maze, &states, &reference.values, target_error, &settings);
+        println!(
+            "maze of {} states: value iteration {sweeps} backups, prioritized sweeping {prioritized}",
+            states.len()
+        );
+
+        let shortest =
+            1 + (reference.values[&maze.start].ln() / settings.gamma.ln()).round() as usize;
+        let params = DynaParameters {
+            alpha: 1.0,
+            planning_steps: 5,
+            steps: 3000 * (scale * scale) as usize,
+            ..Default::default()
+        };
+        let dyna = dyna_q(&maze, Default::default(), &[maze.start], &params);
+        let sweeping =
+            prioritized_sweeping(&maze, Default::default(), &[maze.start], &params, 1e-4);
+        let last = |lengths: &[usize]| {
+            let tail = &lengths[lengths.len().saturating_sub(10)..];
+            tail.iter().sum::<usize>() as f32 / tail.len().max(1) as f32
+        };
+        println!(
+            "  shortest path {shortest}, mean of the last 10 episodes: Dyna-Q {:?} ({} episodes), prioritized sweeping {:?} ({} episodes)",
+            last(&dyna.lengths),
+            dyna.lengths.len(),
+            last(&sweeping.lengths),
+            sweeping.lengths.len()
+        );
+    }
+}
+
+/// LSPI on the Dyna maze from transitions of uniformly chosen state–action pairs, with one-hot
+/// features so that its fixed point is q*. Reports the length of the greedy path from the start.
+pub fn least_squares_solution8_4() {
+    let mut rng = thread_rng();
+    let maze = dyna_maze(1);
+    let states: Vec<MazeState> = maze
+        .open_states()
+        .into_iter()
+        .filter(|s| !maze.is_terminal(s))
+        .collect();
+    let features = TabularFeatures::new(&states);
+    let settings = LeastSquaresSettings {
+        gamma: 0.95,
+        ..Default::default()
+    };
+    for samples in [500, 2000, 10000] {
+        let transitions: Vec<Transition<MazeState, MazeAction>> = (0..samples)
+            .map(|_| {
+                let state = *states.choose(&mut rng).unwrap();
+                let action = *maze.posible_actions(&state).choose(&mut rng).unwrap();
+                let (next_state, reward) = maze.response(&state, &action);
+                Transition {
+                    state,
+                    action,
+                    reward,
+                    next_state,
+                }
+            })
+            .collect();
+        let result = lspi(&maze, &transitions, &features, &settings).unwrap();
+        let mut state = maze.start;
+        let mut length = 0;
+        while !maze.is_terminal(&state) && length < 100 {
+            let action = approximate_greedy_action(
+                &maze,
+                &result.action_values,
+                &features.features(&state),
+                &state,
+            );
+            state = maze.response(&state, &action).0;
+            length += 1;
+        }
+        println!(
+            "{samples} samples: {} LSPI iterations, greedy path of {length} steps (shortest 14)",
+            result.iterations
+        );
+    }
+}
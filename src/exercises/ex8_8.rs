@@ -0,0 +1,79 @@
+/*
+Exercise 8.8: Replicate the experiment whose results are shown in the lower part of Figure 8.8, then
+try the same experiment but with b = 3. Discuss the meaning of your results.
+
+The experiment of Section 8.6 compares two ways of distributing expected updates. The tasks are
+undiscounted episodic tasks generated at random: from each of |S| states two actions are possible,
+each of which results in one of b next states, all equally likely, with a different random
+selection of b states for each state–action pair. On all transitions there is a 0.1 probability of
+transition to the terminal state, ending the episode. The expected reward on each transition is
+selected from a Gaussian distribution with mean 0 and variance 1. In the uniform case updates cycle
+through all state–action pairs, in the on-policy case episodes are simulated from the start state
+following the ε-greedy policy (ε = 0.1) and the pairs encountered are updated. The measure is the
+value of the start state under the greedy policy, averaged over many tasks.
+
+Rewards here are integers, so the Gaussian rewards are multiplied by REWARD_SCALE and rounded and
+the values are divided by it again. On-policy sampling is well ahead early on, most of all with
+b = 1 and many states, while uniform updates end up better in the long run.
+*/
+
+use crate::{
+    bases::{
+        random_mdp::RandomMdp,
+        trajectory_sampling::{compare_update_distributions, UpdateDistributionSettings},
+    },
+    utils::plot::plot_curves,
+};
+
+const REWARD_SCALE: f32 = 100.0;
+
+/// Start state value of the greedy policy against expected updates, uniform and on-policy, for
+/// 1000 states with b = 1, 3, 10 and for 10000 states with b = 1 and b = 3.
+pub fn solution8_8() {
+    let experiments = [
+        (1000, 1, 20_000, 500, 100),
+        (1000, 3, 20_000, 500, 100),
+        (1000, 10, 20_000, 500, 50),
+        (10_000, 1, 200_000, 5000, 50),
+        (10_000, 3, 200_000, 5000, 20),
+    ];
+    for (states, branching, updates, eval_every, tasks) in experiments {
+        let settings = UpdateDistributionSettings {
+            updates,
+            eval_every,
+            tolerance: 1e-3 * REWARD_SCALE,
+            ..Default::default()
+        };
+        let (uniform, on_policy) = compare_update_distributions(
+            tasks,
+            || {
+                let task = RandomMdp::branching(states, 2, branching, 0.1, REWARD_SCALE);
+                let nonterminal = task.nonterminal_states();
+                let start = task.start;
+                (task, nonterminal, start)
+            },
+            &settings,
+        );
+        let scaled = |curve: Vec<f32>| -> Vec<f64> {
+            curve
+                .into_iter()
+                .map(|v| (v / REWARD_SCALE) as f64)
+                .collect()
+        };
+        let (uniform, on_policy) = (scaled(uniform), scaled(on_policy));
+        println!(
+            "{states} states, b = {branching}: after {} updates uniform {:.3}, on-policy {:.3}, after {updates} uniform {:.3}, on-policy {:.3}",
+            updates / 4,
+            uniform[uniform.len() / 4],
+            on_policy[on_policy.len() / 4],
+            uniform.last().unwrap(),
+            on_policy.last().unwrap()
+        );
+        plot_curves(
+            &[("uniform", uniform), ("on-policy", on_policy)],
+            &format!("{states} states, b = {branching}"),
+            &format!("trajectory_sampling_{states}_{branching}.png"),
+        )
+        .unwrap();
+    }
+}
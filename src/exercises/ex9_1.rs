@@ -0,0 +1,489 @@
+/*
+Example 9.1: State Aggregation on the 1000-state Random Walk. Consider a 1000-state version of the
+random walk task. The states are numbered from 1 to 1000, left to right, and all episodes begin near
+the center, in state 500. State transitions are from the current state to one of the 100
+neighboring states to its left, or to one of the 100 neighboring states to its right, all with equal
+probability. Of course, if the current state is near an edge, then there may be fewer than 100
+neighbors on that side of it. In this case, all the probability that would have gone into those
+missing neighbors goes into the probability of terminating on that side. Termination on the left
+produces a reward of −1, and termination on the right produces a reward of +1. All other
+transitions have a reward of zero. Figure 9.1 shows the value function learned by gradient Monte
+Carlo with state aggregation into 10 groups of 100 states, and Figure 9.5 compares the Fourier and
+polynomial bases of orders 5, 10 and 20 on the same task.
+*/
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    bases::{
+        basis_functions::{
+            Bounds, CoarseCoding, FourierBasis, PolynomialBasis, RadialBasis, StateAggregation,
+        },
+        function_approximation::{
+            value_error, FeatureExtractor, Linear, StateVector, ValueFunction,
+        },
+        least_squares::{lstd, LeastSquaresSettings, RecursiveLstd},
+        mdp::{Enviorment, Policy, Transition},
+        neural_network::{Activation, Network, Optimizer},
+        prioritized_sweeping::{full_sweep_planner, PlanningSettings},
+        semi_gradient::{gradient_monte_carlo, semi_gradient_td_zero},
+        tile_coding::TileCoder,
+    },
+    utils::plot::plot_curves,
+};
+
+use super::ex6_2::{WalkAction, WalkState};
+
+impl StateVector for WalkState {
+    fn to_vector(&self) -> Vec<f64> {
+        vec![self.position as f64]
+    }
+}
+
+/// Walk over `size` nonterminal states numbered from 1 that jumps up to `jump` states to either
+/// side, positions 0 and `size + 1` are terminal.
+pub struct JumpWalk {
+    pub size: u32,
+    pub jump: u32,
+}
+
+impl JumpWalk {
+    pub fn start(&self) -> WalkState {
+        WalkState {
+            position: self.size / 2,
+        }
+    }
+
+    pub fn nonterminal_states(&self) -> Vec<WalkState> {
+        (1..=self.size)
+            .map(|position| WalkState { position })
+            .collect()
+    }
+
+    pub fn policy<'a>(&self) -> Policy<'a, WalkState, WalkAction> {
+        let map = self
+            .get_states()
+            .into_iter()
+            .map(|state| (state, WalkAction))
+            .collect();
+        Policy::Deterministic(map)
+    }
+
+    /// Bounds of the nonterminal positions, for the feature families.
+    pub fn bounds(&self) -> Bounds {
+        Bounds::new(vec![1.0], vec![self.size as f64])
+    }
+
+    /// Values computed with sweeps over the known dynamics.
+    pub fn true_values(&self) -> HashMap<WalkState, f32> {
+        let settings = PlanningSettings {
+            theta: 1e-6,
+            ..Default::default()
+        };
+        let states = self.nonterminal_states();
+        let mut values = full_sweep_planner(self, &states, HashMap::new(), &settings, None).values;
+        values.retain(|state, _| state.position >= 1 && state.position <= self.size);
+        values
+    }
+
+    /// Landing position and reward of a jump of `step` states, negative to the left.
+    fn jump_to(&self, state: &WalkState, step: i64) -> (WalkState, i32) {
+        let position = state.position as i64 + step;
+        if position <= 0 {
+            (WalkState { position: 0 }, -1)
+        } else if position > self.size as i64 {
+            (
+                WalkState {
+                    position: self.size + 1,
+                },
+                1,
+            )
+        } else {
+            (
+                WalkState {
+                    position: position as u32,
+                },
+                0,
+            )
+        }
+    }
+}
+
+impl<'a> Enviorment<'a, WalkState, WalkAction> for JumpWalk {
+    fn response(&self, state: &WalkState, action: &WalkAction) -> (WalkState, i32) {
+        let _ = action;
+        if self.is_terminal(state) {
+            return (*state, 0);
+        }
+        let mut rng = thread_rng();
+        let step = rng.gen_range(1..=self.jump as i64);
+        if rng.gen_bool(0.5) {
+            self.jump_to(state, step)
+        } else {
+            self.jump_to(state, -step)
+        }
+    }
+    fn is_terminal(&self, state: &WalkState) -> bool {
+        state.position == 0 || state.position == self.size + 1
+    }
+    fn posible_actions(&self, state: &WalkState) -> Vec<WalkAction> {
+        let _ = state;
+        vec![WalkAction]
+    }
+    fn get_states(&self) -> Vec<WalkState> {
+        (0..=self.size + 1)
+            .map(|position| WalkState { position })
+            .collect()
+    }
+}
+
+/// Known dynamics of the walk so the true values can be computed with the DP solvers.
+impl crate::bases::mdp::EnviormentModel<WalkState, WalkAction> for JumpWalk {
+    fn dynamics(&self, state: &WalkState, action: &WalkAction) -> HashMap<(WalkState, i32), f32> {
+        let _ = action;
+        let mut distribution = HashMap::new();
+        if <Self as Enviorment<WalkState, WalkAction>>::is_terminal(self, state) {
+            return distribution;
+        }
+        let prob = 1.0 / (2 * self.jump) as f32;
+        for step in 1..=self.jump as i64 {
+            for step in [step, -step] {
+                *distribution.entry(self.jump_to(state, step)).or_insert(0.0) += prob;
+            }
+        }
+        distribution
+    }
+    fn posible_actions(&self, state: &WalkState) -> Vec<WalkAction> {
+        <Self as Enviorment<WalkState, WalkAction>>::posible_actions(self, state)
+    }
+    fn get_states(&self) -> Vec<WalkState> {
+        <Self as Enviorment<WalkState, WalkAction>>::get_states(self)
+    }
+    fn response(&self, state: &WalkState, action: &WalkAction) -> (WalkState, i32) {
+        <Self as Enviorment<WalkState, WalkAction>>::response(self, state, action)
+    }
+    fn is_terminal(&self, state: &WalkState) -> bool {
+        <Self as Enviorment<WalkState, WalkAction>>::is_terminal(self, state)
+    }
+}
+
+fn thousand_state_walk() -> JumpWalk {
+    JumpWalk {
+        size: 1000,
+        jump: 100,
+    }
+}
+
+/// Figure 9.1 and the left of Figure 9.2, gradient Monte Carlo and semi-gradient TD(0) with the
+/// states aggregated into 10 groups of 100, plotted against the true values.
+pub fn solution9_1() {
+    let walk = thousand_state_walk();
+    let pol = walk.policy();
+    let truth = walk.true_values();
+    let init_states = [walk.start()];
+    let features = StateAggregation::new(vec![10], walk.bounds());
+
+    let monte_carlo = gradient_monte_carlo(
+        &pol,
+        Linear::new(features.dimension()),
+        &features,
+        &init_states,
+        100_000,
+        &walk,
+        2e-5,
+        1.0,
+    );
+    let td = semi_gradient_td_zero(
+        &pol,
+        Linear::new(features.dimension()),
+        &features,
+        &init_states,
+        100_000,
+        &walk,
+        1e-4,
+        1.0,
+    );
+    println!(
+        "RMS error: gradient MC {:?}, semi-gradient TD(0) {:?}",
+        value_error(&monte_carlo, &features, &truth, None),
+        value_error(&td, &features, &truth, None)
+    );
+
+    let states = walk.nonterminal_states();
+    let curve = |value: &dyn Fn(&WalkState) -> f32| -> Vec<f64> {
+        states.iter().map(|s| value(s) as f64).collect()
+    };
+    plot_curves(
+        &[
+            ("true value", curve(&|s| truth[s])),
+            (
+                "gradient MC",
+                curve(&|s| monte_carlo.value(&features.features(s))),
+            ),
+            (
+                "semi-gradient TD(0)",
+                curve(&|s| td.value(&features.features(s))),
+            ),
+        ],
+        "State aggregation on the 1000-state walk",
+        "state_aggregation.png",
+    )
+    .unwrap();
+}
+
+/// RMS error of gradient Monte Carlo with the given features every `every` episodes, averaged
+/// over runs.
+fn learning_curve(
+    walk: &JumpWalk,
+    features: &dyn FeatureExtractor<WalkState>,
+    alpha: f32,
+    episodes: u32,
+    every: u32,
+    runs: u32,
+    truth: &HashMap<WalkState, f32>,
+) -> Vec<f64> {
+    let pol = walk.policy();
+    let init_states = [walk.start()];
+    let mut curve = vec![0.0; (episodes / every) as usize];
+    for _ in 0..runs {
+        let mut approximation = Linear::new(features.dimension());
+        for point in curve.iter_mut() {
+            approximation = gradient_monte_carlo(
+                &pol,
+                approximation,
+                features,
+                &init_states,
+                every,
+                walk,
+                alpha,
+                1.0,
+            );
+            *point += value_error(&approximation, features, truth, None) as f64 / runs as f64;
+        }
+    }
+    curve
+}
+
+/// Figure 9.5 and Figure 9.10 together, every basis learned by the same gradient Monte Carlo
+/// learner, with radial basis functions and coarse coding added.
+pub fn bases_solution9_1() {
+    let walk = thousand_state_walk();
+    let truth = walk.true_values();
+    let episodes = 5000;
+    let every = 100;
+    let runs = 5;
+    let num_tilings = 50;
+    let tiles = TileCoder::new(num_tilings, vec![1.0], vec![1000.0], vec![5.0], 512);
+    let bases: Vec<(String, Box<dyn FeatureExtractor<WalkState>>, f32)> = vec![
+        (
+            "polynomial 5".to_string(),
+            Box::new(PolynomialBasis::new(5, walk.bounds())),
+            1e-4,
+        ),
+        (
+            "polynomial 10".to_string(),
+            Box::new(PolynomialBasis::new(10, walk.bounds())),
+            1e-4,
+        ),
+        (
+            "polynomial 20".to_string(),
+            Box::new(PolynomialBasis::new(20, walk.bounds())),
+            1e-4,
+        ),
+        (
+            "Fourier 5".to_string(),
+            Box::new(FourierBasis::new(5, walk.bounds())),
+            5e-5,
+        ),
+        (
+            "Fourier 10".to_string(),
+            Box::new(FourierBasis::new(10, walk.bounds())),
+            5e-5,
+        ),
+        (
+            "Fourier 20".to_string(),
+            Box::new(FourierBasis::new(20, walk.bounds())),
+            5e-5,
+        ),
+        (
+            "RBF 21".to_string(),
+            Box::new(RadialBasis::grid(21, 0.05, walk.bounds())),
+            5e-5,
+        ),
+        (
+            "coarse coding 50".to_string(),
+            Box::new(CoarseCoding::intervals(50, 0.2, walk.bounds())),
+            1e-5,
+        ),
+        (
+            "state aggregation 5".to_string(),
+            Box::new(StateAggregation::new(vec![5], walk.bounds())),
+            1e-4,
+        ),
+        (
+            format!("{num_tilings} tilings"),
+            Box::new(tiles),
+            1e-4 / num_tilings as f32,
+        ),
+    ];
+    let mut curves = Vec::new();
+    for (name, features, alpha) in &bases {
+        let curve = learning_curve(
+            &walk,
+            features.as_ref(),
+            *alpha,
+            episodes,
+            every,
+            runs,
+            &truth,
+        );
+        println!(
+            "{name}: RMS error after {episodes} episodes {:?}",
+            curve.last().unwrap()
+        );
+        curves.push((name.as_str(), curve));
+    }
+    plot_curves(&curves, "Bases on the 1000-state walk", "walk_bases.png").unwrap();
+}
+
+/// LSTD(λ) on the 1000-state walk with the Fourier basis of order 10, solved in batch from
+/// recorded episodes and recursively one transition at a time, next to gradient Monte Carlo on the
+/// same number of episodes.
+pub fn least_squares_solution9_1() {
+    let walk = thousand_state_walk();
+    let pol = walk.policy();
+    let truth = walk.true_values();
+    let features = FourierBasis::new(10, walk.bounds());
+    for episodes in [10, 100, 1000] {
+        let data: Vec<Vec<Transition<WalkState, WalkAction>>> = (0..episodes)
+            .map(|_| {
+                let mut state = walk.start();
+                let mut episode = Vec::new();
+                while !walk.is_terminal(&state) {
+                    let action = pol.sample_action(&state);
+                    let (next_state, reward) = walk.response(&state, &action);
+                    episode.push(Transition {
+                        state,
+                        action,
+                        reward,
+                        next_state,
+                    });
+                    state = next_state;
+                }
+                episode
+            })
+            .collect();
+        let mut errors = Vec::new();
+        for lambda in [0.0, 0.8, 1.0] {
+            let settings = LeastSquaresSettings {
+                lambda,
+                ..Default::default()
+            };
+            let batch = lstd(&walk, &data, &features, &settings).unwrap();
+            errors.push((lambda, value_error(&batch, &features, &truth, None)));
+        }
+        let mut recursive = RecursiveLstd::new(features.dimension(), &Default::default());
+        let mut rejected = 0;
+        for episode in &data {
+            for transition in episode {
+                let next = if walk.is_terminal(&transition.next_state) {
+                    vec![0.0; features.dimension()]
+                } else {
+                    features.features(&transition.next_state)
+                };
+                if !recursive.observe(
+                    &features.features(&transition.state),
+                    transition.reward,
+                    &next,
+                ) {
+                    rejected += 1;
+                }
+            }
+            recursive.end_episode();
+        }
+        let monte_carlo = gradient_monte_carlo(
+            &pol,
+            Linear::new(features.dimension()),
+            &features,
+            &[walk.start()],
+            episodes,
+            &walk,
+            5e-5,
+            1.0,
+        );
+        println!(
+            "{episodes} episodes: LSTD(λ) RMS error {errors:?}, recursive LSTD {:?} ({rejected} singular updates skipped), gradient MC {:?}",
+            value_error(&recursive.weights(), &features, &truth, None),
+            value_error(&monte_carlo, &features, &truth, None)
+        );
+    }
+}
+
+/// Gradient Monte Carlo with a network with two tanh hidden layers on the normalized position,
+/// next to state aggregation with the step size of Figure 9.1. The returns are only ever ±1 and
+/// every update of an episode pushes the shared parameters the same way, so the network needs a
+/// step size as small as the linear one and its final error still varies a lot between runs,
+/// from about 0.01 to 0.3. Adam fares worse, it moves every parameter by its full rate on each of
+/// those updates. The trained network is written out as text and read back.
+pub fn network_solution9_1() {
+    let walk = thousand_state_walk();
+    let pol = walk.policy();
+    let truth = walk.true_values();
+    let init_states = [walk.start()];
+    let inputs = PolynomialBasis::new(1, walk.bounds());
+    let episodes = 50_000;
+    let network = gradient_monte_carlo(
+        &pol,
+        Network::new(
+            &[inputs.dimension(), 16, 16, 1],
+            Activation::Tanh,
+            Activation::Identity,
+            Optimizer::sgd(1.0),
+            None,
+        ),
+        &inputs,
+        &init_states,
+        episodes,
+        &walk,
+        1e-5,
+        1.0,
+    );
+    let aggregation = StateAggregation::new(vec![10], walk.bounds());
+    let linear = gradient_monte_carlo(
+        &pol,
+        Linear::new(aggregation.dimension()),
+        &aggregation,
+        &init_states,
+        episodes,
+        &walk,
+        2e-5,
+        1.0,
+    );
+    let reloaded = Network::from_text(&network.to_text(), Optimizer::sgd(1.0)).unwrap();
+    println!(
+        "RMS error after {episodes} episodes: network {:?} ({:?} read back), state aggregation {:?}",
+        value_error(&network, &inputs, &truth, None),
+        value_error(&reloaded, &inputs, &truth, None),
+        value_error(&linear, &aggregation, &truth, None)
+    );
+
+    let states = walk.nonterminal_states();
+    let curve = |value: &dyn Fn(&WalkState) -> f32| -> Vec<f64> {
+        states.iter().map(|s| value(s) as f64).collect()
+    };
+    plot_curves(
+        &[
+            ("true value", curve(&|s| truth[s])),
+            ("network", curve(&|s| network.value(&inputs.features(s)))),
+            (
+                "state aggregation",
+                curve(&|s| linear.value(&aggregation.features(s))),
+            ),
+        ],
+        "A network on the 1000-state walk",
+        "network_walk.png",
+    )
+    .unwrap();
+}
@@ -1,2 +1,14 @@
+pub mod ex10_1;
+pub mod ex10_2;
+pub mod ex11_1;
+pub mod ex12_11;
 pub mod ex4_3;
 pub mod ex5_10;
+pub mod ex6_2;
+pub mod ex6_7;
+pub mod ex7_1;
+pub mod ex8_2;
+pub mod ex8_3;
+pub mod ex8_4;
+pub mod ex8_8;
+pub mod ex9_1;
@@ -0,0 +1,130 @@
+/// Dense row major matrix, enough linear algebra for the least-squares methods without pulling
+/// in a crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Matrix {
+            rows,
+            cols,
+            data: vec![0.0; rows * cols],
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut matrix = Matrix::zeros(size, size);
+        for i in 0..size {
+            matrix.data[i * size + i] = 1.0;
+        }
+        matrix
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.data[row * self.cols + col] = value;
+    }
+
+    pub fn scale(&mut self, factor: f64) {
+        for x in self.data.iter_mut() {
+            *x *= factor;
+        }
+    }
+
+    /// Adds `value` to every diagonal entry, the ridge regularization of a square matrix.
+    pub fn add_diagonal(&mut self, value: f64) {
+        for i in 0..self.rows.min(self.cols) {
+            self.data[i * self.cols + i] += value;
+        }
+    }
+
+    /// self ← self + scale · u vᵀ
+    pub fn add_outer(&mut self, u: &[f64], v: &[f64], scale: f64) {
+        for (i, &ui) in u.iter().enumerate() {
+            if ui == 0.0 {
+                continue;
+            }
+            let row = &mut self.data[i * self.cols..(i + 1) * self.cols];
+            for (x, &vj) in row.iter_mut().zip(v) {
+                *x += scale * ui * vj;
+            }
+        }
+    }
+
+    pub fn mul_vec(&self, v: &[f64]) -> Vec<f64> {
+        self.data
+            .chunks(self.cols)
+            .map(|row| row.iter().zip(v).map(|(a, b)| a * b).sum())
+            .collect()
+    }
+
+    /// vᵀ self
+    pub fn vec_mul(&self, v: &[f64]) -> Vec<f64> {
+        let mut result = vec![0.0; self.cols];
+        for (row, &vi) in self.data.chunks(self.cols).zip(v) {
+            if vi == 0.0 {
+                continue;
+            }
+            for (r, &x) in result.iter_mut().zip(row) {
+                *r += vi * x;
+            }
+        }
+        result
+    }
+
+    /// Solves self · x = b by Gaussian elimination with partial pivoting, None when the matrix is
+    /// singular to working precision.
+    pub fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        let n = self.rows;
+        let mut a = self.data.clone();
+        let mut x = b.to_vec();
+        for col in 0..n {
+            let pivot =
+                (col..n).max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))?;
+            if a[pivot * n + col].abs() < 1e-12 {
+                return None;
+            }
+            if pivot != col {
+                for k in 0..n {
+                    a.swap(pivot * n + k, col * n + k);
+                }
+                x.swap(pivot, col);
+            }
+            for row in col + 1..n {
+                let factor = a[row * n + col] / a[col * n + col];
+                if factor == 0.0 {
+                    continue;
+                }
+                for k in col..n {
+                    a[row * n + k] -= factor * a[col * n + k];
+                }
+                x[row] -= factor * x[col];
+            }
+        }
+        for row in (0..n).rev() {
+            let sum: f64 = (row + 1..n).map(|k| a[row * n + k] * x[k]).sum();
+            x[row] = (x[row] - sum) / a[row * n + row];
+        }
+        Some(x)
+    }
+
+    /// Sherman–Morrison update of an inverse, self = A⁻¹ becomes (A + u vᵀ)⁻¹. Returns false and
+    /// leaves the matrix unchanged when the update would make A singular.
+    pub fn sherman_morrison(&mut self, u: &[f64], v: &[f64]) -> bool {
+        let inverse_u = self.mul_vec(u);
+        let v_inverse = self.vec_mul(v);
+        let denominator = 1.0 + v.iter().zip(&inverse_u).map(|(a, b)| a * b).sum::<f64>();
+        if denominator.abs() < 1e-12 {
+            return false;
+        }
+        self.add_outer(&inverse_u, &v_inverse, -1.0 / denominator);
+        true
+    }
+}
@@ -1 +1,4 @@
+pub mod linear_algebra;
+pub mod plot;
+pub mod ring_buffer;
 pub mod stats;
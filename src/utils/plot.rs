@@ -0,0 +1,56 @@
+use plotters::prelude::*;
+
+/// Draws every curve on the same axes, the x axis is the index of each point.
+pub fn plot_curves(
+    curves: &[(&str, Vec<f64>)],
+    caption: &str,
+    file_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let len = curves.iter().map(|(_, c)| c.len()).max().unwrap_or(1);
+    let min = curves
+        .iter()
+        .flat_map(|(_, c)| c.iter().copied())
+        .fold(f64::INFINITY, f64::min);
+    let max = curves
+        .iter()
+        .flat_map(|(_, c)| c.iter().copied())
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (min, max) = if min < max {
+        (min, max)
+    } else {
+        (min - 1.0, min + 1.0)
+    };
+
+    let root = BitMapBackend::new(file_path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 40).into_font())
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .margin(20)
+        .build_cartesian_2d(0..len, min..max)?;
+
+    chart.configure_mesh().draw()?;
+
+    for (i, (label, curve)) in curves.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart
+            .draw_series(LineSeries::new(
+                curve.iter().enumerate().map(|(x, &y)| (x, y)),
+                color,
+            ))?
+            .label(*label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], color));
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+/// Fixed size buffer indexed by time step, step `t` lives in slot `t % capacity` so only the
+/// last `capacity` steps are kept.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    slots: Vec<Option<T>>,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            slots: (0..capacity.max(1)).map(|_| None).collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn set(&mut self, t: usize, item: T) {
+        let capacity = self.capacity();
+        self.slots[t % capacity] = Some(item);
+    }
+
+    /// Panics if step `t` was never stored.
+    pub fn get(&self, t: usize) -> &T {
+        self.slots[t % self.capacity()].as_ref().unwrap()
+    }
+
+    pub fn clear(&mut self) {
+        for slot in self.slots.iter_mut() {
+            *slot = None;
+        }
+    }
+}